@@ -0,0 +1,61 @@
+use std::mem::transmute;
+
+use itertools::Itertools;
+
+use crate::{changes::Status, data::team_name, DevTrait, ScoreRow};
+
+/// For every group and tier, how far each player ended up from the cutoff:
+/// holders by how much they kept their trait by, and the players one level
+/// below by how far they were from being promoted. Everything is in whatever
+/// the group is ranked by, closest to the cutoff first.
+///
+/// The cutoff is the lowest-ranked player who holds the trait on their own
+/// merit, i.e. kept it or was promoted to it, so protected and warned players
+/// and anyone demoted down to it don't drag it down.
+pub fn watch(score_rows: &[ScoreRow]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (group, rows) in &score_rows.iter().group_by(|row| &row.group) {
+        let rows = rows.collect_vec();
+        for dev in [DevTrait::XFactor, DevTrait::Superstar, DevTrait::Star] {
+            let below = unsafe { transmute::<u8, DevTrait>(dev as u8 - 1) };
+            let Some(cutoff) = rows
+                .iter()
+                .filter(|row| {
+                    row.newDevTrait == dev
+                        && (row.status == Status::Unchanged
+                            || (row.status == Status::Changed
+                                && (row.oldDevTrait as u8) < row.newDevTrait as u8))
+                })
+                .map(|row| row.rankingScore)
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+            else {
+                continue;
+            };
+            lines.push(format!("{group} {dev:?}, cutoff {cutoff:.2}:"));
+            for row in rows
+                .iter()
+                .filter(|row| row.newDevTrait == dev || row.newDevTrait == below)
+                .sorted_by(|a, b| {
+                    (a.rankingScore - cutoff)
+                        .abs()
+                        .partial_cmp(&(b.rankingScore - cutoff).abs())
+                        .unwrap()
+                })
+            {
+                lines.push(format!(
+                    "{} ({}): {:?}, {:+.2}{}",
+                    row.fullName,
+                    team_name(&row.team),
+                    row.newDevTrait,
+                    row.rankingScore - cutoff,
+                    match row.status {
+                        Status::Unchanged => String::new(),
+                        status => format!(" ({})", status.to_string().to_lowercase()),
+                    }
+                ));
+            }
+            lines.push(String::new());
+        }
+    }
+    lines
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    mem::transmute,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data::PlayerData, DevTrait};
+
+/// (Name, Team, Position)
+pub type PlayerKey = (String, String, String);
+
+pub fn key(player: &PlayerData) -> PlayerKey {
+    (
+        player.fullName.clone(),
+        player.team.clone(),
+        player.position.clone(),
+    )
+}
+
+/// What happened to a player this season. These are written to the CSV and the
+/// history file, so the names can't change without breaking old seasons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Status {
+    Unchanged,
+    /// Kept out of demotions, e.g. a rookie or someone who just devved up.
+    Protected,
+    Changed,
+    /// Fell below a demotion cutoff but still had strikes to spare.
+    Warned,
+    /// Stepped down a level by `age_decline`.
+    #[serde(rename = "Age decline")]
+    AgeDecline,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Unchanged => write!(f, "Unchanged"),
+            Status::Protected => write!(f, "Protected"),
+            Status::Changed => write!(f, "Changed"),
+            Status::Warned => write!(f, "Warned"),
+            Status::AgeDecline => write!(f, "Age decline"),
+        }
+    }
+}
+
+/// Every promotion, demotion, warning and protection so far this season.
+pub struct Changes {
+    /// Players who can't be demoted, or changed again, this season.
+    protected: HashSet<PlayerKey>,
+    /// Players who have already moved up this season, in game or by promotion.
+    upgraded: HashSet<PlayerKey>,
+    /// (OldDev, NewDev)
+    pub changed: HashMap<PlayerKey, (DevTrait, DevTrait)>,
+    /// Players who fell below a demotion cutoff but don't have enough strikes
+    /// yet, (Dev, Strike)
+    pub warned: HashMap<PlayerKey, (DevTrait, u8)>,
+    /// Players stepped down by `age_decline`.
+    age_declined: HashSet<PlayerKey>,
+    /// Seasons in a row each player has been warned, going back from the last
+    /// recorded season
+    strikes: HashMap<u32, u8>,
+    demotion_strikes: u8,
+}
+
+impl Changes {
+    pub fn new(strikes: HashMap<u32, u8>, demotion_strikes: u8) -> Self {
+        Self {
+            protected: HashSet::new(),
+            upgraded: HashSet::new(),
+            changed: HashMap::new(),
+            warned: HashMap::new(),
+            age_declined: HashSet::new(),
+            strikes,
+            demotion_strikes,
+        }
+    }
+
+    pub fn protect(&mut self, player: &PlayerData) {
+        self.protected.insert(key(player));
+    }
+
+    pub fn is_protected(&self, player: &PlayerData) -> bool {
+        self.protected.contains(&key(player))
+    }
+
+    /// For players whose dev trait went up in game since the old export.
+    pub fn devved_up(&mut self, player: &PlayerData) {
+        self.protect(player);
+        self.upgraded.insert(key(player));
+    }
+
+    pub fn is_upgraded(&self, player: &PlayerData) -> bool {
+        self.upgraded.contains(&key(player))
+    }
+
+    pub fn is_changed(&self, player: &PlayerData) -> bool {
+        self.changed.contains_key(&key(player))
+    }
+
+    pub fn promote(&mut self, player: &PlayerData, to: DevTrait) {
+        self.devved_up(player);
+        self.changed.insert(
+            key(player),
+            (unsafe { transmute::<u8, DevTrait>(player.devTrait) }, to),
+        );
+    }
+
+    /// Demotes a player one level for falling below a cutoff, unless
+    /// `demotion_strikes` means they're only warned this season. Either way
+    /// they're protected from anything else this season. Returns whether they
+    /// were actually demoted.
+    pub fn demote(&mut self, player: &PlayerData) -> bool {
+        self.protect(player);
+        let strike = self.strikes.get(&player.rosterId).copied().unwrap_or(0) + 1;
+        if strike < self.demotion_strikes {
+            self.warned.insert(
+                key(player),
+                (
+                    unsafe { transmute::<u8, DevTrait>(player.devTrait) },
+                    strike,
+                ),
+            );
+            return false;
+        }
+        self.force_demote(player, player.devTrait);
+        true
+    }
+
+    /// Demotes a player one level from `from`, which may already be lower than
+    /// their dev trait in the old export. Strikes aren't checked.
+    pub fn force_demote(&mut self, player: &PlayerData, from: u8) {
+        self.protect(player);
+        self.changed.insert(
+            key(player),
+            (
+                unsafe { transmute::<u8, DevTrait>(player.devTrait) },
+                unsafe { transmute::<u8, DevTrait>(from - 1) },
+            ),
+        );
+    }
+
+    pub fn age_decline(&mut self, player: &PlayerData) {
+        self.force_demote(player, player.devTrait);
+        self.age_declined.insert(key(player));
+    }
+
+    pub fn is_age_declined(&self, key: &PlayerKey) -> bool {
+        self.age_declined.contains(key)
+    }
+
+    /// The player's dev trait after everything so far this season.
+    pub fn current_dev(&self, player: &PlayerData) -> u8 {
+        self.changed
+            .get(&key(player))
+            .map(|(_, new)| *new as u8)
+            .unwrap_or(player.devTrait)
+    }
+
+    pub fn status(&self, player: &PlayerData) -> Status {
+        let key = key(player);
+        if self.changed.contains_key(&key) {
+            if self.age_declined.contains(&key) {
+                Status::AgeDecline
+            } else {
+                Status::Changed
+            }
+        } else if self.warned.contains_key(&key) {
+            Status::Warned
+        } else if self.protected.contains(&key) {
+            Status::Protected
+        } else {
+            Status::Unchanged
+        }
+    }
+}
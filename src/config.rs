@@ -0,0 +1,765 @@
+use std::{collections::HashMap, fs::read_to_string};
+
+use serde::{Deserialize, Serialize};
+
+use crate::DevTrait;
+
+/// Optional league config. Anything not set in here falls back to the defaults
+/// below.
+pub const CONFIG_PATH: &str = "data/config.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Keyed by position group, e.g. `[defense_weights.EDGE]`.
+    pub defense_weights: HashMap<String, DefenseWeights>,
+    /// Which scoring model each position group uses, e.g. `FB = "ol"` or
+    /// `WR = { formula = "recTotalYds * 0.1" }`.
+    pub scorers: HashMap<String, ScorerConfig>,
+    /// How much of each group's score comes from season totals vs per-game
+    /// production, e.g. `[scoring_mode.WR]`.
+    pub scoring_mode: HashMap<String, ScoringMode>,
+    /// The length of the regular season, used to scale per-game scores back up
+    /// to a full season so they can be blended with totals.
+    pub season_games: u8,
+    /// What players are ranked by within their group.
+    pub rank_by: RankBy,
+    /// How much of each group's ranking comes from `playerBestOvr` rather than
+    /// stats, from 0 to 1, e.g. `QB = 0.2`.
+    pub ovr_blend: HashMap<String, f32>,
+    /// Overrides for which group each raw Madden position is judged in, e.g.
+    /// `[positions.FS]`. Positions not listed here keep the built-in mapping
+    /// for the detected version of Madden.
+    pub positions: HashMap<String, PositionConfig>,
+    /// How many of each dev trait a position group should have, e.g.
+    /// `[limits.QB]`. Any group a position is mapped to needs limits.
+    pub limits: HashMap<String, DevLimits>,
+    /// Teams running a 3-4, whose positions use `three_four_group` if they have
+    /// one.
+    pub three_four_teams: Vec<String>,
+    /// Optionally puts front seven players in a group by their archetype, e.g.
+    /// `[classifier.archetypes.LE]`.
+    pub classifier: ClassifierConfig,
+    /// Earlier seasons' stat exports to blend into each player's score, e.g.
+    /// `{ dir = "neon_season_2024", weight = 0.5 }`. The current season always
+    /// has a weight of 1.
+    pub prior_seasons: Vec<PriorSeason>,
+    /// How many seasons in a row a player has to fall below a demotion cutoff
+    /// before they're actually demoted. Until then they're warned. Anything
+    /// above 1 needs seasons recorded with `--record`.
+    pub demotion_strikes: u8,
+    /// How to handle players whose position is different in the new players
+    /// export than the old one.
+    pub position_changes: PositionChangePolicy,
+    /// How players without a team count toward each group's quota.
+    pub free_agents: FreeAgentPolicy,
+    /// The most of each dev trait any one team can have, e.g. `[team_caps]`.
+    pub team_caps: Option<TeamCaps>,
+    /// A points budget for each team's dev traits, e.g. `[team_budget]`.
+    pub team_budget: Option<TeamBudget>,
+    /// Lets a player just below a tier take the trait from the weakest holder
+    /// even when the group is within its limits, e.g. `[merit_swaps]`.
+    pub merit_swaps: Option<MeritSwaps>,
+    /// Who can be promoted to fill a minimum, e.g. `[promotion_rules]`.
+    pub promotion_rules: PromotionRules,
+    /// Steps older XFactors and Superstars down a tier, e.g. `[age_decline]`.
+    pub age_decline: Option<AgeDecline>,
+    /// How close to the last safe player's ranking, in percent, a player picked
+    /// for demotion has to be to be spared, e.g. `grace_band = 3.0`. The group
+    /// is left over its maximum by however many are spared.
+    pub grace_band: Option<f32>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let contents = match read_to_string(CONFIG_PATH) {
+            Ok(contents) => contents,
+            Err(_) => return Self::default(),
+        };
+        let mut config: Config = toml::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse {CONFIG_PATH}: {e}"));
+
+        // Groups the user didn't mention keep their default profile, rather than
+        // the whole table being replaced.
+        let mut defense_weights = Self::default().defense_weights;
+        defense_weights.extend(config.defense_weights);
+        config.defense_weights = defense_weights;
+        let mut scorers = Self::default().scorers;
+        scorers.extend(config.scorers);
+        config.scorers = scorers;
+        let mut limits = Self::default().limits;
+        limits.extend(config.limits);
+        config.limits = limits;
+        let mut archetypes = ClassifierConfig::default().archetypes;
+        archetypes.extend(config.classifier.archetypes);
+        config.classifier.archetypes = archetypes;
+
+        // Demoting has to free up points, or a team over budget could never
+        // get back under it
+        if let Some(TeamBudget {
+            costs: DevCosts { xf, ss, star },
+            ..
+        }) = config.team_budget
+        {
+            assert!(
+                0 < star && star < ss && ss < xf,
+                "team_budget costs must go up with each tier (0 < star < ss < xf), got star \
+                 {star}, ss {ss}, xf {xf}"
+            );
+        }
+        // Players under `min_games` are scored on their totals alone, so the
+        // blend has to add up to one total or the two wouldn't be comparable
+        for (group, mode) in &config.scoring_mode {
+            assert!(
+                (mode.total + mode.per_game - 1.0).abs() < 1e-4,
+                "scoring_mode for {group} must have total + per_game = 1, got total {} and \
+                 per_game {}",
+                mode.total,
+                mode.per_game
+            );
+        }
+
+        config
+    }
+
+    pub fn defense_weights(&self, group: &str) -> DefenseWeights {
+        self.defense_weights.get(group).copied().unwrap_or_default()
+    }
+
+    pub fn scoring_mode(&self, group: &str) -> ScoringMode {
+        self.scoring_mode.get(group).copied().unwrap_or_default()
+    }
+
+    pub fn ovr_blend(&self, group: &str) -> f32 {
+        let weight = self.ovr_blend.get(group).copied().unwrap_or_default();
+        assert!(
+            (0.0..=1.0).contains(&weight),
+            "ovr_blend for {group} must be between 0 and 1, got {weight}"
+        );
+        weight
+    }
+
+    pub fn limits(&self, group: &str) -> DevLimits {
+        *self
+            .limits
+            .get(group)
+            .unwrap_or_else(|| panic!("No limits configured for position group {group}"))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let base = DefenseWeights::default();
+        Self {
+            defense_weights: HashMap::from([
+                (
+                    "IDL".to_string(),
+                    DefenseWeights {
+                        tackle: 0.75,
+                        deflection: 0.5,
+                        sack: 2.5,
+                        ..base
+                    },
+                ),
+                (
+                    "EDGE".to_string(),
+                    DefenseWeights {
+                        tackle: 0.4,
+                        deflection: 0.5,
+                        sack: 3.0,
+                        forced_fumble: 2.5,
+                        ..base
+                    },
+                ),
+                (
+                    "LB".to_string(),
+                    DefenseWeights {
+                        tackle: 0.75,
+                        sack: 1.5,
+                        ..base
+                    },
+                ),
+                (
+                    "CB".to_string(),
+                    DefenseWeights {
+                        tackle: 0.25,
+                        deflection: 1.5,
+                        catch_allowed: -0.3,
+                        sack: 1.0,
+                        int: 4.0,
+                        ..base
+                    },
+                ),
+                (
+                    "S".to_string(),
+                    DefenseWeights {
+                        deflection: 1.25,
+                        catch_allowed: -0.25,
+                        int: 3.5,
+                        ..base
+                    },
+                ),
+            ]),
+            scorers: HashMap::from([
+                ("QB".to_string(), ScorerConfig::Builtin(ScorerKind::Qb)),
+                (
+                    "HB".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Receiver),
+                ),
+                (
+                    "FB".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Receiver),
+                ),
+                (
+                    "WR".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Receiver),
+                ),
+                (
+                    "TE".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Receiver),
+                ),
+                ("OL".to_string(), ScorerConfig::Builtin(ScorerKind::Ol)),
+                (
+                    "IDL".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Defense),
+                ),
+                (
+                    "EDGE".to_string(),
+                    ScorerConfig::Builtin(ScorerKind::Defense),
+                ),
+                ("LB".to_string(), ScorerConfig::Builtin(ScorerKind::Defense)),
+                ("CB".to_string(), ScorerConfig::Builtin(ScorerKind::Defense)),
+                ("S".to_string(), ScorerConfig::Builtin(ScorerKind::Defense)),
+                ("K".to_string(), ScorerConfig::Builtin(ScorerKind::Kicker)),
+                ("P".to_string(), ScorerConfig::Builtin(ScorerKind::Punter)),
+            ]),
+            scoring_mode: HashMap::new(),
+            season_games: 17,
+            rank_by: RankBy::Score,
+            ovr_blend: HashMap::new(),
+            positions: HashMap::new(),
+            limits: HashMap::from([
+                (
+                    "QB".to_string(),
+                    DevLimits {
+                        xf_min: 3,
+                        xf_max: 4,
+                        ss_min: 6,
+                        ss_max: 8,
+                        star_min: 12,
+                        star_max: 20,
+                    },
+                ),
+                (
+                    "HB".to_string(),
+                    DevLimits {
+                        xf_min: 2,
+                        xf_max: 4,
+                        ss_min: 8,
+                        ss_max: 12,
+                        star_min: 30,
+                        star_max: 40,
+                    },
+                ),
+                (
+                    "FB".to_string(),
+                    DevLimits {
+                        xf_min: 0,
+                        xf_max: 0,
+                        ss_min: 0,
+                        ss_max: 2,
+                        star_min: 3,
+                        star_max: 5,
+                    },
+                ),
+                (
+                    "WR".to_string(),
+                    DevLimits {
+                        xf_min: 8,
+                        xf_max: 10,
+                        ss_min: 12,
+                        ss_max: 18,
+                        star_min: 45,
+                        star_max: 60,
+                    },
+                ),
+                (
+                    "TE".to_string(),
+                    DevLimits {
+                        xf_min: 2,
+                        xf_max: 4,
+                        ss_min: 6,
+                        ss_max: 8,
+                        star_min: 24,
+                        star_max: 32,
+                    },
+                ),
+                (
+                    "OL".to_string(),
+                    DevLimits {
+                        xf_min: 0,
+                        xf_max: 0,
+                        ss_min: 12,
+                        ss_max: 18,
+                        star_min: 75,
+                        star_max: 90,
+                    },
+                ),
+                (
+                    "IDL".to_string(),
+                    DevLimits {
+                        xf_min: 4,
+                        xf_max: 6,
+                        ss_min: 10,
+                        ss_max: 12,
+                        star_min: 36,
+                        star_max: 50,
+                    },
+                ),
+                (
+                    "EDGE".to_string(),
+                    DevLimits {
+                        xf_min: 4,
+                        xf_max: 6,
+                        ss_min: 8,
+                        ss_max: 12,
+                        star_min: 25,
+                        star_max: 40,
+                    },
+                ),
+                (
+                    "LB".to_string(),
+                    DevLimits {
+                        xf_min: 2,
+                        xf_max: 4,
+                        ss_min: 6,
+                        ss_max: 10,
+                        star_min: 24,
+                        star_max: 36,
+                    },
+                ),
+                (
+                    "CB".to_string(),
+                    DevLimits {
+                        xf_min: 3,
+                        xf_max: 6,
+                        ss_min: 10,
+                        ss_max: 16,
+                        star_min: 45,
+                        star_max: 60,
+                    },
+                ),
+                (
+                    "S".to_string(),
+                    DevLimits {
+                        xf_min: 3,
+                        xf_max: 5,
+                        ss_min: 8,
+                        ss_max: 12,
+                        star_min: 36,
+                        star_max: 50,
+                    },
+                ),
+                (
+                    "K".to_string(),
+                    DevLimits {
+                        xf_min: 0,
+                        xf_max: 0,
+                        ss_min: 1,
+                        ss_max: 3,
+                        star_min: 5,
+                        star_max: 8,
+                    },
+                ),
+                (
+                    "P".to_string(),
+                    DevLimits {
+                        xf_min: 0,
+                        xf_max: 0,
+                        ss_min: 1,
+                        ss_max: 2,
+                        star_min: 3,
+                        star_max: 6,
+                    },
+                ),
+            ]),
+            three_four_teams: [
+                "Broncos",
+                "Browns",
+                "Buccaneers",
+                "Cardinals",
+                "Cowboys",
+                "Dolphins",
+                "Giants",
+                "Jaguars",
+                "Packers",
+                "Patriots",
+                "Texans",
+                "Titans",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+            classifier: ClassifierConfig::default(),
+            prior_seasons: Vec::new(),
+            demotion_strikes: 1,
+            position_changes: PositionChangePolicy::New,
+            free_agents: FreeAgentPolicy::Include,
+            team_caps: None,
+            team_budget: None,
+            merit_swaps: None,
+            promotion_rules: PromotionRules::default(),
+            age_decline: None,
+            grace_band: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScorerConfig {
+    Builtin(ScorerKind),
+    /// A user-defined formula, see `expr.rs` for the syntax.
+    Formula {
+        formula: String,
+    },
+}
+
+/// The built-in scoring models. See `scoring.rs` for what each one looks at.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScorerKind {
+    /// Passing and rushing production.
+    Qb,
+    /// Receiving and rushing production.
+    Receiver,
+    /// Overall rating with a bit of randomness, for positions without stats.
+    Ol,
+    /// Defensive production, using the group's `defense_weights`.
+    Defense,
+    Kicker,
+    Punter,
+}
+
+/// Points awarded per defensive stat. Any field left out of a profile in the
+/// config uses the value from `Default`, not the group's default profile.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DefenseWeights {
+    pub tackle: f32,
+    pub deflection: f32,
+    pub catch_allowed: f32,
+    pub sack: f32,
+    pub td: f32,
+    pub forced_fumble: f32,
+    pub fumble_rec: f32,
+    pub int: f32,
+    pub int_return_yard: f32,
+    pub safety: f32,
+}
+
+impl Default for DefenseWeights {
+    fn default() -> Self {
+        Self {
+            tackle: 0.5,
+            deflection: 1.0,
+            catch_allowed: -0.2,
+            sack: 2.0,
+            td: 6.0,
+            forced_fumble: 2.0,
+            fumble_rec: 2.0,
+            int: 3.0,
+            int_return_yard: 0.05,
+            safety: 3.0,
+        }
+    }
+}
+
+/// Within a group these all give the same order, apart from how ties fall, but
+/// z-scores and percentiles are comparable across groups.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankBy {
+    Score,
+    ZScore,
+    Percentile,
+}
+
+/// By default players are scored on their season totals, which punishes anyone
+/// who missed time with an injury. `per_game` scores them on what they did in
+/// the games they actually played instead, and the two can be blended. `total`
+/// and `per_game` have to add up to 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringMode {
+    pub total: f32,
+    pub per_game: f32,
+    /// Players with fewer games than this are scored on totals only, so a
+    /// backup with one big game doesn't get extrapolated to a full season.
+    pub min_games: u8,
+}
+
+impl Default for ScoringMode {
+    fn default() -> Self {
+        Self {
+            total: 1.0,
+            per_game: 0.0,
+            min_games: 4,
+        }
+    }
+}
+
+/// One entry in the position catalog, see `positions.rs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionConfig {
+    pub group: String,
+    pub three_four_group: Option<String>,
+    /// Where the position goes when listing players. Leaving this out keeps
+    /// the built-in order, or puts new positions last.
+    pub sort_order: Option<u8>,
+}
+
+/// These are the total number of each dev trait a group should have, and
+/// include players at higher tiers, e.g. `star_max` counts Superstars and
+/// XFactors too.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DevLimits {
+    pub xf_min: usize,
+    pub xf_max: usize,
+    pub ss_min: usize,
+    pub ss_max: usize,
+    pub star_min: usize,
+    pub star_max: usize,
+}
+
+/// A CB moved to S in the offseason still has a season of CB stats, so judging
+/// them against safeties isn't really fair either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionChangePolicy {
+    /// Judge them at the position they played during the season, from the old
+    /// players export.
+    Season,
+    /// Judge them at their new position.
+    New,
+    /// Protect them from any changes this season.
+    Exempt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreeAgentPolicy {
+    /// Free agents are judged with everyone else in their group.
+    Include,
+    /// Free agents don't count toward quotas and are never changed.
+    ExcludeCounts,
+    /// Free agents count toward quotas and can be demoted, but are never
+    /// promoted to fill a minimum.
+    ExcludePromotions,
+    /// Free agents get a share of the group's maximums based on how many of the
+    /// group they make up, and are only compared with each other. They have no
+    /// minimums, so nobody is promoted just because they're a free agent.
+    Separate,
+}
+
+/// Each cap counts players at that tier or higher, so `ss` includes XFactors.
+/// Leaving one out means that tier isn't capped.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamCaps {
+    pub xf: Option<usize>,
+    pub ss: Option<usize>,
+    pub star: Option<usize>,
+}
+
+/// Anything left out isn't checked. These only apply to promotions that fill a
+/// minimum or a merit swap, not to players who devved up in game.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromotionRules {
+    /// Compared with `playerBestOvr`.
+    pub min_ovr: Option<u8>,
+    pub max_age: Option<u8>,
+    /// Games played this season. Not checked for OL, since there are no OL
+    /// stats.
+    pub min_games: Option<u8>,
+    /// Leaves out free agents and practice squad players.
+    pub rostered_only: bool,
+}
+
+/// This is applied after the quotas and doesn't count toward them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AgeDecline {
+    /// Players older than this are stepped down.
+    pub age: u8,
+    /// Anyone who finished this high in their group keeps their trait.
+    #[serde(default)]
+    pub top_n: usize,
+}
+
+/// `margin` is in whatever players are ranked by, so with `rank_by =
+/// "percentile"` it's percentile points.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeritSwaps {
+    /// How much better the challenger has to be than the holder.
+    pub margin: f32,
+    /// Across every tier in the group.
+    pub max_per_group: usize,
+}
+
+impl Default for MeritSwaps {
+    fn default() -> Self {
+        Self {
+            margin: 0.0,
+            max_per_group: 1,
+        }
+    }
+}
+
+/// Each team can spend up to `points` on dev traits, with each trait costing
+/// what's set in `costs`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TeamBudget {
+    pub points: usize,
+    #[serde(default)]
+    pub costs: DevCosts,
+    /// Who gets demoted first when a team is over budget.
+    #[serde(default)]
+    pub order: BudgetOrder,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevCosts {
+    pub xf: usize,
+    pub ss: usize,
+    pub star: usize,
+}
+
+impl Default for DevCosts {
+    fn default() -> Self {
+        Self {
+            xf: 3,
+            ss: 2,
+            star: 1,
+        }
+    }
+}
+
+impl DevCosts {
+    pub fn cost(&self, dev: DevTrait) -> usize {
+        match dev {
+            DevTrait::XFactor => self.xf,
+            DevTrait::Superstar => self.ss,
+            DevTrait::Star => self.star,
+            DevTrait::Normal => 0,
+        }
+    }
+}
+
+/// Each demotion is only one level, so demoting an XFactor frees up
+/// `xf - ss` points, not all of `xf`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetOrder {
+    /// The team's lowest-ranked trait holders, whatever their trait.
+    #[default]
+    LowestScore,
+    /// Stars first, then Superstars, then XFactors, lowest-ranked first within
+    /// each.
+    LowestTrait,
+    /// XFactors first, then Superstars, then Stars, lowest-ranked first within
+    /// each.
+    HighestTrait,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorSeason {
+    /// A folder of stat exports in `data`, laid out the same as `neon_season`.
+    pub dir: String,
+    pub weight: f32,
+}
+
+/// Listed positions don't always match how a player is used, e.g. a 4-3 LE who
+/// is really a run stuffing tackle. With this enabled, positions listed in
+/// `archetypes` are grouped by the player's archetype first, falling back to
+/// the position catalog for archetypes that aren't listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    pub enabled: bool,
+    /// Keyed by raw position, then archetype, e.g. `"Run Stopper" = "IDL"`.
+    pub archetypes: HashMap<String, HashMap<String, String>>,
+    /// Puts a player in a group no matter their position or archetype, keyed
+    /// by rosterId. These apply even if the classifier isn't enabled.
+    pub overrides: HashMap<String, String>,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        let lineman = HashMap::from([
+            ("Power Rusher".to_string(), "EDGE".to_string()),
+            ("Speed Rusher".to_string(), "EDGE".to_string()),
+            ("Run Stopper".to_string(), "IDL".to_string()),
+        ]);
+        let linebacker = HashMap::from([
+            ("Power Rusher".to_string(), "EDGE".to_string()),
+            ("Speed Rusher".to_string(), "EDGE".to_string()),
+            ("Run Stopper".to_string(), "LB".to_string()),
+            ("Field General".to_string(), "LB".to_string()),
+            ("Pass Coverage".to_string(), "LB".to_string()),
+        ]);
+        Self {
+            enabled: false,
+            // Both Madden 24 and 25 names, since only one set will show up
+            archetypes: ["LE", "RE", "LEDGE", "REDGE"]
+                .into_iter()
+                .map(|position| (position.to_string(), lineman.clone()))
+                .chain(
+                    ["LOLB", "ROLB", "SAM", "WILL"]
+                        .into_iter()
+                        .map(|position| (position.to_string(), linebacker.clone())),
+                )
+                .collect(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl DevLimits {
+    /// A `share` of these limits, e.g. for the free agents in a group.
+    pub fn scaled(&self, share: f32) -> Self {
+        let scale = |limit: usize| (limit as f32 * share).round() as usize;
+        Self {
+            xf_min: scale(self.xf_min),
+            xf_max: scale(self.xf_max),
+            ss_min: scale(self.ss_min),
+            ss_max: scale(self.ss_max),
+            star_min: scale(self.star_min),
+            star_max: scale(self.star_max),
+        }
+    }
+
+    /// Just the maximums, for a pool that shouldn't be filled up to a minimum.
+    pub fn maximums(&self) -> Self {
+        Self {
+            xf_min: 0,
+            ss_min: 0,
+            star_min: 0,
+            ..*self
+        }
+    }
+
+    /// What's left of these limits after `other` is taken out.
+    pub fn minus(&self, other: &Self) -> Self {
+        Self {
+            xf_min: self.xf_min - other.xf_min,
+            xf_max: self.xf_max - other.xf_max,
+            ss_min: self.ss_min - other.ss_min,
+            ss_max: self.ss_max - other.ss_max,
+            star_min: self.star_min - other.star_min,
+            star_max: self.star_max - other.star_max,
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+};
+
+use csv::Reader;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+/// All of the stat exports for a single season.
+pub struct SeasonStats {
+    pub passing: Vec<PassingData>,
+    pub receiving: Vec<ReceivingData>,
+    pub rushing: Vec<RushingData>,
+    pub defense: Vec<DefenseData>,
+    pub kicking: Vec<KickingData>,
+    pub punting: Vec<PuntingData>,
+}
+
+impl SeasonStats {
+    pub fn load(dir: &str) -> Self {
+        Self {
+            passing: read_csv(&format!("{dir}/SFDL_passing.csv")),
+            receiving: read_csv(&format!("{dir}/SFDL_receiving.csv")),
+            rushing: read_csv(&format!("{dir}/SFDL_rushing.csv")),
+            defense: read_csv(&format!("{dir}/SFDL_defense.csv")),
+            kicking: read_csv(&format!("{dir}/SFDL_kicking.csv")),
+            punting: read_csv(&format!("{dir}/SFDL_punting.csv")),
+        }
+    }
+
+    pub fn passing(&self, rosterId: u32) -> Option<&PassingData> {
+        self.passing
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    pub fn receiving(&self, rosterId: u32) -> Option<&ReceivingData> {
+        self.receiving
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    pub fn rushing(&self, rosterId: u32) -> Option<&RushingData> {
+        self.rushing
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    pub fn defense(&self, rosterId: u32) -> Option<&DefenseData> {
+        self.defense
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    pub fn kicking(&self, rosterId: u32) -> Option<&KickingData> {
+        self.kicking
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    pub fn punting(&self, rosterId: u32) -> Option<&PuntingData> {
+        self.punting
+            .iter()
+            .find(|stat| stat.player__rosterId == rosterId)
+    }
+
+    /// The stats are exported alongside the old players, so if rosterIds were
+    /// reassigned since then, the stats need to follow the player to their new
+    /// id. `ids` maps old ids to new ones for every matched player. Stats for
+    /// anyone else are dropped, since their old id may now belong to someone
+    /// else entirely.
+    pub fn remap_ids(&mut self, ids: &HashMap<u32, u32>) {
+        remap(&mut self.passing, ids, |stat| &mut stat.player__rosterId);
+        remap(&mut self.receiving, ids, |stat| &mut stat.player__rosterId);
+        remap(&mut self.rushing, ids, |stat| &mut stat.player__rosterId);
+        remap(&mut self.defense, ids, |stat| &mut stat.player__rosterId);
+        remap(&mut self.kicking, ids, |stat| &mut stat.player__rosterId);
+        remap(&mut self.punting, ids, |stat| &mut stat.player__rosterId);
+    }
+
+    /// The most games the player shows up for in any of the stat exports. OL
+    /// don't have stats, so this is always 0 for them.
+    pub fn games_played(&self, rosterId: u32) -> u8 {
+        self.passing
+            .iter()
+            .filter(|stat| stat.player__rosterId == rosterId)
+            .map(|stat| stat.gamesPlayed)
+            .sum::<u8>()
+            .max(
+                self.rushing
+                    .iter()
+                    .filter(|stat| stat.player__rosterId == rosterId)
+                    .map(|stat| stat.gamesPlayed)
+                    .sum(),
+            )
+            .max(
+                self.receiving
+                    .iter()
+                    .filter(|stat| stat.player__rosterId == rosterId)
+                    .map(|stat| stat.gamesPlayed)
+                    .sum(),
+            )
+            .max(
+                self.defense
+                    .iter()
+                    .filter(|stat| stat.player__rosterId == rosterId)
+                    .map(|stat| stat.gamesPlayed)
+                    .sum(),
+            )
+            .max(
+                self.kicking
+                    .iter()
+                    .filter(|stat| stat.player__rosterId == rosterId)
+                    .map(|stat| stat.gamesPlayed)
+                    .sum(),
+            )
+            .max(
+                self.punting
+                    .iter()
+                    .filter(|stat| stat.player__rosterId == rosterId)
+                    .map(|stat| stat.gamesPlayed)
+                    .sum(),
+            )
+    }
+}
+
+/// Free agents don't have a team in the exports.
+pub fn team_name(team: &str) -> String {
+    if team.is_empty() {
+        "Free Agent".to_string()
+    } else {
+        team.to_string()
+    }
+}
+
+fn remap<T>(stats: &mut Vec<T>, ids: &HashMap<u32, u32>, roster_id: fn(&mut T) -> &mut u32) {
+    stats.retain_mut(|stat| match ids.get(roster_id(stat)) {
+        Some(new) => {
+            *roster_id(stat) = *new;
+            true
+        }
+        None => false,
+    });
+}
+
+pub fn read_csv<T: DeserializeOwned>(filename: &str) -> Vec<T> {
+    let file = BufReader::new(File::open(filename).unwrap());
+    let mut reader = Reader::from_reader(file);
+    reader.deserialize().map(|rec| rec.unwrap()).collect()
+}
+
+/// Reads a players export. Every column is kept in `attributes`, including the
+/// ones that have typed fields, so anything else in Neon's export (ratings,
+/// contract details, etc.) is still available by name.
+pub fn read_players(filename: &str) -> Vec<PlayerData> {
+    let file = BufReader::new(File::open(filename).unwrap());
+    let mut reader = Reader::from_reader(file);
+    let headers = reader.headers().unwrap().clone();
+    reader
+        .records()
+        .map(|rec| {
+            let rec = rec.unwrap();
+            let mut player: PlayerData = rec.deserialize(Some(&headers)).unwrap();
+            player.attributes = headers
+                .iter()
+                .zip(rec.iter())
+                .map(|(header, value)| (header.to_string(), value.to_string()))
+                .collect();
+            player
+        })
+        .collect()
+}
+
+/// Columns in the players export that don't have a typed field usable in
+/// formulas, and whether every value in that column is a number.
+pub fn extra_player_columns(players: &[PlayerData]) -> BTreeMap<String, bool> {
+    let mut columns = BTreeMap::new();
+    for player in players {
+        for (name, value) in &player.attributes {
+            if PlayerData::COLUMNS.contains(&name.as_str()) {
+                continue;
+            }
+            let numeric = value.is_empty() || value.parse::<f32>().is_ok();
+            *columns.entry(name.clone()).or_insert(true) &= numeric;
+        }
+    }
+    columns
+}
+
+fn from_str_bool<'de, D>(de: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(de)?;
+    if s == "True" {
+        Ok(true)
+    } else if s == "False" {
+        Ok(false)
+    } else {
+        panic!("Unexpected value for bool");
+    }
+}
+
+fn from_str_opt_bool<'de, D>(de: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(de)?;
+    if s.is_empty() {
+        return Ok(None);
+    }
+    if s == "True" {
+        Ok(Some(true))
+    } else if s == "False" {
+        Ok(Some(false))
+    } else {
+        panic!("Unexpected value for bool");
+    }
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+pub struct PlayerData {
+    pub rosterId: u32,
+    pub team: String,
+    #[serde(deserialize_with = "from_str_bool")]
+    pub isRetired: bool,
+    pub age: u8,
+    pub fullName: String,
+    pub position: String,
+    pub playerBestOvr: u8,
+    pub yearsPro: u8,
+    pub devTrait: u8,
+
+    // Everything below may or may not be in the export, depending on what Neon
+    // gives us, so none of it can be relied on.
+    #[serde(default)]
+    pub firstName: Option<String>,
+    #[serde(default)]
+    pub lastName: Option<String>,
+    #[serde(default)]
+    pub archetype: Option<String>,
+    #[serde(default)]
+    pub college: Option<String>,
+    #[serde(default)]
+    pub birthDay: Option<u8>,
+    #[serde(default)]
+    pub birthMonth: Option<u8>,
+    #[serde(default)]
+    pub birthYear: Option<u16>,
+    #[serde(default)]
+    pub jerseyNum: Option<u8>,
+    #[serde(default)]
+    pub draftRound: Option<u8>,
+    #[serde(default)]
+    pub draftPick: Option<u16>,
+    #[serde(default)]
+    pub rookieYear: Option<u16>,
+    #[serde(default)]
+    pub contractYearsLeft: Option<u8>,
+    #[serde(default)]
+    pub contractSalary: Option<u32>,
+    #[serde(default, deserialize_with = "from_str_opt_bool")]
+    pub isOnPracticeSquad: Option<bool>,
+    #[serde(default, deserialize_with = "from_str_opt_bool")]
+    pub isFreeAgent: Option<bool>,
+    #[serde(default)]
+    pub injuryLength: Option<u8>,
+
+    /// Every column from the export by header name, typed or not.
+    #[serde(skip)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl PlayerData {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .get(name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn attribute_f32(&self, name: &str) -> Option<f32> {
+        self.attribute(name).and_then(|value| value.parse().ok())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PassingData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub passTotalAtt: u32,
+    pub passTotalComp: u32,
+    pub passAvgCompPct: f32,
+    pub passTotalInts: u32,
+    pub passTotalLongest: i32,
+    pub passerAvgRating: f32,
+    pub passTotalSacks: u32,
+    pub passTotalTDs: u32,
+    pub passTotalYds: i32,
+    pub passAvgYdsPerAtt: f32,
+    pub passAvgYdsPerGame: f32,
+}
+
+#[derive(Deserialize)]
+pub struct ReceivingData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub recTotalCatches: u32,
+    pub recAvgCatchPct: f32,
+    pub recTotalDrops: u32,
+    pub recTotalLongest: u32,
+    pub recTotalTDs: u32,
+    pub recTotalYdsAfterCatch: i32,
+    pub recTotalYds: i32,
+    pub recAvgYacPerCatch: f32,
+    pub recAvgYdsPerCatch: f32,
+    pub recAvgYdsPerGame: f32,
+}
+
+#[derive(Deserialize)]
+pub struct RushingData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub rushTotalAtt: u32,
+    pub rushTotalBrokenTackles: u32,
+    pub rushTotalFum: u32,
+    pub rushTotalLongest: i32,
+    pub rushTotalTDs: u32,
+    pub rushTotal20PlusYds: u32,
+    pub rushTotalYdsAfterContact: i32,
+    pub rushTotalYds: i32,
+    pub rushAvgYdsAfterContact: f32,
+    pub rushAvgYdsPerAtt: f32,
+    pub rushAvgYdsPerGame: f32,
+}
+
+#[derive(Deserialize)]
+pub struct DefenseData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub defTotalCatchAllowed: u32,
+    pub defTotalDeflections: u32,
+    pub defTotalForcedFum: u32,
+    pub defTotalFumRec: u32,
+    pub defTotalInts: u32,
+    pub defTotalIntReturnYds: u32,
+    pub defTotalSacks: f32,
+    pub defTotalSafeties: u32,
+    pub defTotalTDs: u32,
+    pub defTotalTackles: f32,
+}
+
+#[derive(Deserialize)]
+pub struct KickingData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub fGTotalAtt: u32,
+    pub fGTotal50PlusAtt: u32,
+    pub fGTotal50PlusMade: u32,
+    pub fGTotalLongest: u32,
+    pub fGTotalMade: u32,
+    pub fGAvgCompPct: f32,
+    pub kickoffTotalAtt: u32,
+    pub kickoffTotalTBs: u32,
+    pub xPTotalAtt: u32,
+    pub xPTotalMade: u32,
+    pub xPAvgCompPct: f32,
+}
+
+#[derive(Deserialize)]
+pub struct PuntingData {
+    pub player__rosterId: u32,
+    pub gamesPlayed: u8,
+    pub puntsTotalBlocked: u32,
+    pub puntsTotalIn20: u32,
+    pub puntTotalLongest: i32,
+    pub puntTotalTBs: u32,
+    pub puntAvgNetYdsPerAtt: f32,
+    pub puntTotalNetYds: i32,
+    pub puntTotalAtt: u32,
+    pub puntAvgYdsPerAtt: f32,
+    pub puntTotalYds: i32,
+}
+
+/// Numeric columns that can be referenced by name from a scoring formula.
+pub trait Columns {
+    /// The name used to qualify a column, e.g. `passing.gamesPlayed`.
+    const TABLE: &'static str;
+    const COLUMNS: &'static [&'static str];
+
+    fn column(&self, index: usize) -> f32;
+}
+
+macro_rules! columns {
+    ($ty:ty, $table:literal, [$($field:ident),* $(,)?]) => {
+        impl Columns for $ty {
+            const TABLE: &'static str = $table;
+            const COLUMNS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            fn column(&self, index: usize) -> f32 {
+                [$(self.$field as f32),*][index]
+            }
+        }
+    };
+}
+
+// Any other column from the players export can be used in formulas too, see
+// `extra_player_columns`.
+columns!(
+    PlayerData,
+    "player",
+    [age, playerBestOvr, yearsPro, devTrait]
+);
+columns!(
+    PassingData,
+    "passing",
+    [
+        gamesPlayed,
+        passTotalAtt,
+        passTotalComp,
+        passAvgCompPct,
+        passTotalInts,
+        passTotalLongest,
+        passerAvgRating,
+        passTotalSacks,
+        passTotalTDs,
+        passTotalYds,
+        passAvgYdsPerAtt,
+        passAvgYdsPerGame,
+    ]
+);
+columns!(
+    ReceivingData,
+    "receiving",
+    [
+        gamesPlayed,
+        recTotalCatches,
+        recAvgCatchPct,
+        recTotalDrops,
+        recTotalLongest,
+        recTotalTDs,
+        recTotalYdsAfterCatch,
+        recTotalYds,
+        recAvgYacPerCatch,
+        recAvgYdsPerCatch,
+        recAvgYdsPerGame,
+    ]
+);
+columns!(
+    RushingData,
+    "rushing",
+    [
+        gamesPlayed,
+        rushTotalAtt,
+        rushTotalBrokenTackles,
+        rushTotalFum,
+        rushTotalLongest,
+        rushTotalTDs,
+        rushTotal20PlusYds,
+        rushTotalYdsAfterContact,
+        rushTotalYds,
+        rushAvgYdsAfterContact,
+        rushAvgYdsPerAtt,
+        rushAvgYdsPerGame,
+    ]
+);
+columns!(
+    DefenseData,
+    "defense",
+    [
+        gamesPlayed,
+        defTotalCatchAllowed,
+        defTotalDeflections,
+        defTotalForcedFum,
+        defTotalFumRec,
+        defTotalInts,
+        defTotalIntReturnYds,
+        defTotalSacks,
+        defTotalSafeties,
+        defTotalTDs,
+        defTotalTackles,
+    ]
+);
+columns!(
+    KickingData,
+    "kicking",
+    [
+        gamesPlayed,
+        fGTotalAtt,
+        fGTotal50PlusAtt,
+        fGTotal50PlusMade,
+        fGTotalLongest,
+        fGTotalMade,
+        fGAvgCompPct,
+        kickoffTotalAtt,
+        kickoffTotalTBs,
+        xPTotalAtt,
+        xPTotalMade,
+        xPAvgCompPct,
+    ]
+);
+columns!(
+    PuntingData,
+    "punting",
+    [
+        gamesPlayed,
+        puntsTotalBlocked,
+        puntsTotalIn20,
+        puntTotalLongest,
+        puntTotalTBs,
+        puntAvgNetYdsPerAtt,
+        puntTotalNetYds,
+        puntTotalAtt,
+        puntAvgYdsPerAtt,
+        puntTotalYds,
+    ]
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identities;
+
+    fn rows<T: DeserializeOwned>(csv: &str) -> Vec<T> {
+        Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .map(|row| row.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn remap_ids_drops_stats_of_unmatched_ids() {
+        let header =
+            "rosterId,team,isRetired,age,fullName,position,playerBestOvr,yearsPro,devTrait\n";
+        let players_old: Vec<PlayerData> = rows(&format!(
+            "{header}\
+             1,Bears,False,25,Moved Punter,P,70,3,0\n\
+             2,Lions,False,30,Retired Punter,P,70,8,0\n"
+        ));
+        // The moved punter took over id 2, and a rookie was given id 1
+        let players_new: Vec<PlayerData> = rows(&format!(
+            "{header}\
+             2,Bears,False,26,Moved Punter,P,71,4,0\n\
+             1,Lions,False,21,Rookie Punter,P,60,0,0\n"
+        ));
+        let identities = Identities::new(&players_old, &players_new);
+        let mut stats = SeasonStats {
+            passing: Vec::new(),
+            receiving: Vec::new(),
+            rushing: Vec::new(),
+            defense: Vec::new(),
+            kicking: Vec::new(),
+            punting: rows(
+                "player__rosterId,gamesPlayed,puntsTotalBlocked,puntsTotalIn20,puntTotalLongest,\
+                 puntTotalTBs,puntAvgNetYdsPerAtt,puntTotalNetYds,puntTotalAtt,puntAvgYdsPerAtt,\
+                 puntTotalYds\n\
+                 1,17,0,0,0,0,0,0,80,0,0\n\
+                 2,17,0,0,0,0,0,0,20,0,0\n",
+            ),
+        };
+        stats.remap_ids(&identities.matched_ids());
+
+        assert_eq!(stats.punting.len(), 1);
+        assert_eq!(stats.punting(2).unwrap().puntTotalAtt, 80);
+        assert!(stats.punting(1).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{io::stdout, mem::transmute};
+
+use csv::Writer;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    data::{read_players, team_name, PlayerData},
+    identity::Identities,
+    DevTrait,
+};
+
+/// Everything about a player that changed between the two snapshots, in the
+/// order the sections are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum ChangeKind {
+    DevTrait,
+    Position,
+    Team,
+    Retired,
+    /// In the old snapshot but not the new one.
+    Missing,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotChange {
+    pub rosterId: u32,
+    pub fullName: String,
+    /// The player's team and position in the new snapshot, or the old one if
+    /// they're missing from it.
+    pub team: String,
+    pub position: String,
+    pub change: ChangeKind,
+    pub old: String,
+    pub new: String,
+}
+
+/// Players are paired up with `Identities`, so this still works if rosterIds
+/// were reassigned.
+pub fn diff(players_new: &[PlayerData], identities: &Identities) -> Vec<SnapshotChange> {
+    let mut changes = Vec::new();
+    for new in players_new {
+        let Some(old) = identities.old(new) else {
+            continue;
+        };
+        let mut push = |change, old_value: String, new_value: String| {
+            changes.push(SnapshotChange {
+                rosterId: new.rosterId,
+                fullName: new.fullName.clone(),
+                team: new.team.clone(),
+                position: new.position.clone(),
+                change,
+                old: old_value,
+                new: new_value,
+            })
+        };
+        if old.devTrait != new.devTrait {
+            push(
+                ChangeKind::DevTrait,
+                format!("{:?}", unsafe { transmute::<u8, DevTrait>(old.devTrait) }),
+                format!("{:?}", unsafe { transmute::<u8, DevTrait>(new.devTrait) }),
+            );
+        }
+        if old.position != new.position {
+            push(
+                ChangeKind::Position,
+                old.position.clone(),
+                new.position.clone(),
+            );
+        }
+        if old.team != new.team {
+            push(ChangeKind::Team, team_name(&old.team), team_name(&new.team));
+        }
+        if !old.isRetired && new.isRetired {
+            push(ChangeKind::Retired, String::new(), String::new());
+        }
+    }
+    for old in &identities.unmatched_old {
+        changes.push(SnapshotChange {
+            rosterId: old.rosterId,
+            fullName: old.fullName.clone(),
+            team: old.team.clone(),
+            position: old.position.clone(),
+            change: ChangeKind::Missing,
+            old: String::new(),
+            new: String::new(),
+        });
+    }
+    changes
+}
+
+/// Handles `diff` and `diff --csv`.
+pub fn run(args: &[String]) {
+    let players_old = read_players("data/neon_players_old/SFDL_players.csv");
+    let players_new = read_players("data/neon_players_new/SFDL_players.csv");
+    let identities = Identities::new(&players_old, &players_new);
+    identities.report(&players_new);
+    let changes = diff(&players_new, &identities);
+
+    if args.iter().any(|arg| arg == "--csv") {
+        let mut writer = Writer::from_writer(stdout());
+        for change in &changes {
+            writer.serialize(change).unwrap();
+        }
+        writer.flush().unwrap();
+        return;
+    }
+
+    for (kind, group) in &changes
+        .iter()
+        .sorted_by(|a, b| (a.change, &a.team, &a.fullName).cmp(&(b.change, &b.team, &b.fullName)))
+        .group_by(|change| change.change)
+    {
+        println!(
+            "{}:",
+            match kind {
+                ChangeKind::DevTrait => "Dev trait changes",
+                ChangeKind::Position => "Position changes",
+                ChangeKind::Team => "Team changes",
+                ChangeKind::Retired => "Retired",
+                ChangeKind::Missing => "Missing from the new export",
+            }
+        );
+        for change in group {
+            match kind {
+                ChangeKind::Retired | ChangeKind::Missing => println!(
+                    "{} {} {}",
+                    team_name(&change.team),
+                    change.position,
+                    change.fullName
+                ),
+                _ => println!(
+                    "{} {} {}: {} -> {}",
+                    team_name(&change.team),
+                    change.position,
+                    change.fullName,
+                    change.old,
+                    change.new
+                ),
+            }
+        }
+        println!();
+    }
+}
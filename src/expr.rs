@@ -0,0 +1,622 @@
+//! A tiny arithmetic language for user-defined scoring formulas, e.g.
+//! `recTotalYds * 0.1 + recTotalTDs * 4`.
+//!
+//! Formulas are parsed and every column is resolved when the config is loaded,
+//! so a typo fails immediately instead of quietly scoring everyone as 0.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use crate::data::{
+    Columns, DefenseData, KickingData, PassingData, PlayerData, PuntingData, ReceivingData,
+    RushingData, SeasonStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Table {
+    Player,
+    Passing,
+    Receiving,
+    Rushing,
+    Defense,
+    Kicking,
+    Punting,
+}
+
+impl Table {
+    const ALL: [Table; 7] = [
+        Table::Player,
+        Table::Passing,
+        Table::Receiving,
+        Table::Rushing,
+        Table::Defense,
+        Table::Kicking,
+        Table::Punting,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Table::Player => PlayerData::TABLE,
+            Table::Passing => PassingData::TABLE,
+            Table::Receiving => ReceivingData::TABLE,
+            Table::Rushing => RushingData::TABLE,
+            Table::Defense => DefenseData::TABLE,
+            Table::Kicking => KickingData::TABLE,
+            Table::Punting => PuntingData::TABLE,
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Table::Player => PlayerData::COLUMNS,
+            Table::Passing => PassingData::COLUMNS,
+            Table::Receiving => ReceivingData::COLUMNS,
+            Table::Rushing => RushingData::COLUMNS,
+            Table::Defense => DefenseData::COLUMNS,
+            Table::Kicking => KickingData::COLUMNS,
+            Table::Punting => PuntingData::COLUMNS,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Column {
+    Field {
+        table: Table,
+        index: usize,
+    },
+    /// Any other column from the players export, e.g. `speedRating` or
+    /// `contractYearsLeft`, looked up by name.
+    Attribute(String),
+}
+
+impl Column {
+    /// Players without a row in a stat export, or with a blank value in the
+    /// players export, get 0 for that column, same as the built-in scorers.
+    fn value(&self, player: &PlayerData, stats: &SeasonStats) -> f32 {
+        let id = player.rosterId;
+        let (table, index) = match self {
+            Column::Field { table, index } => (*table, *index),
+            Column::Attribute(name) => return player.attribute_f32(name).unwrap_or(0.0),
+        };
+        match table {
+            Table::Player => Some(player.column(index)),
+            Table::Passing => stats.passing(id).map(|s| s.column(index)),
+            Table::Receiving => stats.receiving(id).map(|s| s.column(index)),
+            Table::Rushing => stats.rushing(id).map(|s| s.column(index)),
+            Table::Defense => stats.defense(id).map(|s| s.column(index)),
+            Table::Kicking => stats.kicking(id).map(|s| s.column(index)),
+            Table::Punting => stats.punting(id).map(|s| s.column(index)),
+        }
+        .unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Func {
+    Min,
+    Max,
+    Abs,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f32),
+    Column(Column),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, player: &PlayerData, stats: &SeasonStats) -> f32 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Column(column) => column.value(player, stats),
+            Expr::Neg(a) => -a.eval(player, stats),
+            Expr::Add(a, b) => a.eval(player, stats) + b.eval(player, stats),
+            Expr::Sub(a, b) => a.eval(player, stats) - b.eval(player, stats),
+            Expr::Mul(a, b) => a.eval(player, stats) * b.eval(player, stats),
+            // Rates like TDs per attempt would otherwise be NaN for every player
+            // without a row, so anything divided by 0 counts as 0
+            Expr::Div(a, b) => match b.eval(player, stats) {
+                0.0 => 0.0,
+                divisor => a.eval(player, stats) / divisor,
+            },
+            Expr::Call(func, args) => {
+                let mut args = args.iter().map(|arg| arg.eval(player, stats));
+                match func {
+                    Func::Min => args.fold(f32::INFINITY, f32::min),
+                    Func::Max => args.fold(f32::NEG_INFINITY, f32::max),
+                    Func::Abs => args.next().unwrap().abs(),
+                }
+            }
+        }
+    }
+}
+
+/// A parsed formula, ready to be evaluated for any player.
+#[derive(Debug, Clone)]
+pub struct Formula {
+    expr: Expr,
+}
+
+impl Formula {
+    /// `player_columns` are the extra columns found in the players export, from
+    /// `extra_player_columns`.
+    pub fn parse(
+        source: &str,
+        player_columns: &BTreeMap<String, bool>,
+    ) -> Result<Self, FormulaError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: source.len(),
+            player_columns,
+        };
+        let expr = parser.expr()?;
+        if let Some((token, offset)) = parser.tokens.get(parser.pos) {
+            return Err(FormulaError::new(
+                format!("unexpected {token} after end of formula"),
+                *offset,
+            ));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn eval(&self, player: &PlayerData, stats: &SeasonStats) -> f32 {
+        self.expr.eval(player, stats)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FormulaError {
+    pub message: String,
+    /// Byte offset into the formula where the problem is.
+    pub offset: usize,
+}
+
+impl FormulaError {
+    fn new(message: String, offset: usize) -> Self {
+        Self { message, offset }
+    }
+}
+
+impl Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.offset + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number `{n}`"),
+            Token::Ident(name) => write!(f, "`{name}`"),
+            Token::Plus => write!(f, "`+`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Star => write!(f, "`*`"),
+            Token::Slash => write!(f, "`/`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Dot => write!(f, "`.`"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, FormulaError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = source[start..end].parse().map_err(|_| {
+                FormulaError::new(format!("invalid number `{}`", &source[start..end]), start)
+            })?;
+            tokens.push((Token::Number(number), start));
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Ident(source[start..end].to_string()), start));
+            continue;
+        }
+        let token = match c {
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '.' => Token::Dot,
+            _ => return Err(FormulaError::new(format!("unexpected `{c}`"), start)),
+        };
+        tokens.push((token, start));
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Used for errors about running out of input.
+    end: usize,
+    player_columns: &'a BTreeMap<String, bool>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Result<(Token, usize), FormulaError> {
+        let token =
+            self.tokens.get(self.pos).cloned().ok_or_else(|| {
+                FormulaError::new("unexpected end of formula".to_string(), self.end)
+            })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), FormulaError> {
+        let (token, offset) = self.next()?;
+        if token != expected {
+            return Err(FormulaError::new(
+                format!("expected {expected}, found {token}"),
+                offset,
+            ));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr, FormulaError> {
+        let mut lhs = self.term()?;
+        loop {
+            lhs = match self.peek() {
+                Some(Token::Plus) => {
+                    self.pos += 1;
+                    Expr::Add(Box::new(lhs), Box::new(self.term()?))
+                }
+                Some(Token::Minus) => {
+                    self.pos += 1;
+                    Expr::Sub(Box::new(lhs), Box::new(self.term()?))
+                }
+                _ => return Ok(lhs),
+            };
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, FormulaError> {
+        let mut lhs = self.unary()?;
+        loop {
+            lhs = match self.peek() {
+                Some(Token::Star) => {
+                    self.pos += 1;
+                    Expr::Mul(Box::new(lhs), Box::new(self.unary()?))
+                }
+                Some(Token::Slash) => {
+                    self.pos += 1;
+                    Expr::Div(Box::new(lhs), Box::new(self.unary()?))
+                }
+                _ => return Ok(lhs),
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, FormulaError> {
+        if self.peek() == Some(&Token::Minus) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, FormulaError> {
+        let (token, offset) = self.next()?;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::LParen => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Ident(name) => match self.peek() {
+                Some(Token::LParen) => self.call(&name, offset),
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    let column_offset = self.offset();
+                    match self.next()? {
+                        (Token::Ident(column), _) => self
+                            .resolve_column(Some(&name), &column, offset)
+                            .map(Expr::Column),
+                        (token, _) => Err(FormulaError::new(
+                            format!("expected a column name after `{name}.`, found {token}"),
+                            column_offset,
+                        )),
+                    }
+                }
+                _ => self.resolve_column(None, &name, offset).map(Expr::Column),
+            },
+            token => Err(FormulaError::new(
+                format!("expected a number, column or `(`, found {token}"),
+                offset,
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, offset: usize) -> Result<Expr, FormulaError> {
+        let func = match name {
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "abs" => Func::Abs,
+            _ => {
+                return Err(FormulaError::new(
+                    format!("unknown function `{name}`, expected one of min, max, abs"),
+                    offset,
+                ))
+            }
+        };
+        self.expect(Token::LParen)?;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+
+        let arity_ok = match func {
+            Func::Min | Func::Max => args.len() >= 2,
+            Func::Abs => args.len() == 1,
+        };
+        if !arity_ok {
+            return Err(FormulaError::new(
+                format!(
+                    "`{name}` takes {}, got {}",
+                    if matches!(func, Func::Abs) {
+                        "1 argument"
+                    } else {
+                        "at least 2 arguments"
+                    },
+                    args.len()
+                ),
+                offset,
+            ));
+        }
+        Ok(Expr::Call(func, args))
+    }
+
+    /// Looks up a column by name. Unqualified names must only exist in one
+    /// export, e.g. `gamesPlayed` is in all of the stat exports so it has to be
+    /// written as `defense.gamesPlayed` or similar.
+    fn resolve_column(
+        &self,
+        qualifier: Option<&str>,
+        name: &str,
+        offset: usize,
+    ) -> Result<Column, FormulaError> {
+        let tables = match qualifier {
+            Some(qualifier) => {
+                let table = Table::ALL
+                    .into_iter()
+                    .find(|table| table.name() == qualifier)
+                    .ok_or_else(|| {
+                        FormulaError::new(
+                            format!(
+                                "unknown export `{qualifier}`, expected one of {}",
+                                Table::ALL.map(Table::name).join(", ")
+                            ),
+                            offset,
+                        )
+                    })?;
+                vec![table]
+            }
+            None => Table::ALL.to_vec(),
+        };
+
+        let mut matches = tables
+            .iter()
+            .filter_map(|&table| {
+                table
+                    .columns()
+                    .iter()
+                    .position(|column| *column == name)
+                    .map(|index| (table, Column::Field { table, index }))
+            })
+            .collect::<Vec<_>>();
+        let extra = self.player_columns.get(name).copied();
+        if tables.contains(&Table::Player) && extra.is_some() {
+            if extra == Some(false) {
+                return Err(FormulaError::new(
+                    format!("column `{name}` is not numeric and can't be used in a formula"),
+                    offset,
+                ));
+            }
+            matches.push((Table::Player, Column::Attribute(name.to_string())));
+        }
+
+        match matches.len() {
+            1 => Ok(matches.pop().unwrap().1),
+            0 => Err(FormulaError::new(
+                format!("unknown column `{name}`"),
+                offset,
+            )),
+            _ => Err(FormulaError::new(
+                format!(
+                    "column `{name}` is in more than one export, write it as one of {}",
+                    matches
+                        .iter()
+                        .map(|(table, _)| format!("`{}.{name}`", table.name()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                offset,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::Reader;
+
+    use super::*;
+
+    fn player() -> PlayerData {
+        Reader::from_reader(
+            "rosterId,team,isRetired,age,fullName,position,playerBestOvr,yearsPro,devTrait\n\
+             1,Bears,False,25,Test Player,QB,80,3,1\n"
+                .as_bytes(),
+        )
+        .deserialize()
+        .next()
+        .unwrap()
+        .unwrap()
+    }
+
+    fn stats() -> SeasonStats {
+        SeasonStats {
+            passing: Vec::new(),
+            receiving: Vec::new(),
+            rushing: Vec::new(),
+            defense: Vec::new(),
+            kicking: Vec::new(),
+            punting: Vec::new(),
+        }
+    }
+
+    fn parse(source: &str) -> Result<Formula, FormulaError> {
+        Formula::parse(source, &BTreeMap::new())
+    }
+
+    fn eval(source: &str) -> f32 {
+        parse(source)
+            .unwrap_or_else(|e| panic!("{source}: {e}"))
+            .eval(&player(), &stats())
+    }
+
+    #[test]
+    fn operator_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("8 / 4 / 2"), 1.0);
+        assert_eq!(eval("2 * 3 + 4 / 2"), 8.0);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(eval("-2 * 3"), -6.0);
+        assert_eq!(eval("2 - -3"), 5.0);
+        assert_eq!(eval("--2"), 2.0);
+        assert_eq!(eval("-(1 + 2)"), -3.0);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("min(3, 1, 2)"), 1.0);
+        assert_eq!(eval("max(3, 1, 2)"), 3.0);
+        assert_eq!(eval("abs(1 - 4)"), 3.0);
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(eval("playerBestOvr * 2"), 160.0);
+        // Players without a stat row count as 0
+        assert_eq!(eval("passing.passTotalYds + 1"), 1.0);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(eval("1 / 0"), 0.0);
+        assert_eq!(eval("0 / 0"), 0.0);
+        assert_eq!(
+            eval("passing.passTotalTDs / passing.passTotalAtt * 100"),
+            0.0
+        );
+        assert_eq!(eval("1 + 4 / (2 - 2)"), 1.0);
+    }
+
+    #[test]
+    fn unknown_identifiers() {
+        let error = |source: &str| parse(source).err().unwrap().message;
+        assert!(error("notAColumn").contains("unknown column"));
+        assert!(error("nope.passTotalYds").contains("unknown export"));
+        assert!(error("sqrt(4)").contains("unknown function"));
+        assert!(error("gamesPlayed").contains("more than one export"));
+    }
+
+    #[test]
+    fn malformed_input() {
+        for source in [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 + 2)",
+            "1 2",
+            "1 $ 2",
+            "1.2.3",
+            "* 2",
+            "min(1)",
+            "abs(1, 2)",
+            "min(1,)",
+            "passing.",
+            "passing.1",
+        ] {
+            assert!(parse(source).is_err(), "{source:?} should fail to parse");
+        }
+    }
+
+    #[test]
+    fn error_offsets() {
+        assert_eq!(parse("1 + $").err().unwrap().offset, 4);
+        assert_eq!(parse("1 +").err().unwrap().offset, 3);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{read_to_string, OpenOptions},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{changes::Status, config::Config, DevTrait, ScoreRow};
+
+/// One line per recorded season. Lines are only ever appended, never
+/// rewritten, so an old season's results stay exactly as they were posted.
+pub const HISTORY_PATH: &str = "data/history.jsonl";
+
+#[derive(Serialize, Deserialize)]
+pub struct SeasonRecord {
+    /// Hash of the old players file, which is also what seeds the RNG.
+    pub inputs_hash: String,
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    pub config: Config,
+    pub players: Vec<ScoreRow>,
+}
+
+impl SeasonRecord {
+    pub fn new(inputs_hash: String, config: Config, players: Vec<ScoreRow>) -> Self {
+        Self {
+            inputs_hash,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            config,
+            players,
+        }
+    }
+}
+
+pub fn load() -> Vec<SeasonRecord> {
+    let contents = match read_to_string(HISTORY_PATH) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("Failed to parse line {} of {HISTORY_PATH}: {e}", i + 1))
+        })
+        .collect()
+}
+
+/// Checked before doing anything else, so a season that's already recorded
+/// doesn't get as far as printing results.
+pub fn check_not_recorded(inputs_hash: &str) {
+    if load()
+        .iter()
+        .any(|season| season.inputs_hash == inputs_hash)
+    {
+        panic!(
+            "This season is already in {HISTORY_PATH}. Remove its line from the file to record it \
+             again."
+        );
+    }
+}
+
+pub fn append(record: &SeasonRecord) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_PATH)
+        .unwrap();
+    writeln!(file, "{}", serde_json::to_string(record).unwrap()).unwrap();
+}
+
+/// How many seasons in a row each player has been warned, counting back from
+/// the most recent season. The season being run is skipped if it's already
+/// recorded, so re-running it doesn't count against anyone twice.
+pub fn strikes(seasons: &[SeasonRecord], inputs_hash: &str) -> HashMap<u32, u8> {
+    let mut strikes = HashMap::new();
+    let mut cleared = HashSet::new();
+    for season in seasons
+        .iter()
+        .rev()
+        .filter(|season| season.inputs_hash != inputs_hash)
+    {
+        for row in &season.players {
+            if cleared.contains(&row.rosterId) {
+                continue;
+            }
+            if row.status == Status::Warned {
+                *strikes.entry(row.rosterId).or_insert(0) += 1;
+            } else {
+                cleared.insert(row.rosterId);
+            }
+        }
+    }
+    strikes
+}
+
+/// Handles `history player <rosterId or name>` and `history team <team>`.
+pub fn query(args: &[String]) {
+    let seasons = load();
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("player"), Some(player)) => print_player(&seasons, player),
+        (Some("team"), Some(team)) => print_team(&seasons, team),
+        _ => {
+            eprintln!("Usage: madden-demotion history player <rosterId or name>");
+            eprintln!("       madden-demotion history team <team>");
+        }
+    }
+}
+
+fn print_player(seasons: &[SeasonRecord], player: &str) {
+    let roster_id = player.parse::<u32>().ok();
+    for (i, season) in seasons.iter().enumerate() {
+        for row in season.players.iter().filter(|row| match roster_id {
+            Some(roster_id) => row.rosterId == roster_id,
+            None => row.fullName == player,
+        }) {
+            println!(
+                "Season {} ({}): {} {} {} ({}) = {:.2}, {:.0}%: {}",
+                i + 1,
+                &season.inputs_hash[..8],
+                row.team,
+                row.position,
+                row.fullName,
+                row.group,
+                row.score,
+                row.percentile,
+                describe(row)
+            );
+        }
+    }
+}
+
+fn print_team(seasons: &[SeasonRecord], team: &str) {
+    // [gained, lost] for XFactor, Superstar, Star
+    let mut totals = [[0usize; 2]; 3];
+    for (i, season) in seasons.iter().enumerate() {
+        println!("Season {} ({}):", i + 1, &season.inputs_hash[..8]);
+        for row in season
+            .players
+            .iter()
+            .filter(|row| row.team == team && row.oldDevTrait != row.newDevTrait)
+        {
+            println!(
+                "{} {}: {:?} -> {:?}",
+                row.position, row.fullName, row.oldDevTrait, row.newDevTrait
+            );
+            let (old, new) = (row.oldDevTrait as usize, row.newDevTrait as usize);
+            for (tier, total) in totals.iter_mut().enumerate() {
+                // tier 0 is XFactor (3), down to Star (1)
+                let level = 3 - tier;
+                if old < level && new >= level {
+                    total[0] += 1;
+                } else if old >= level && new < level {
+                    total[1] += 1;
+                }
+            }
+        }
+        println!();
+    }
+    for (trait_, [gained, lost]) in [DevTrait::XFactor, DevTrait::Superstar, DevTrait::Star]
+        .into_iter()
+        .zip(totals)
+    {
+        println!("{trait_:?}: gained {gained}, lost {lost}");
+    }
+}
+
+fn describe(row: &ScoreRow) -> String {
+    match row.status {
+        Status::Changed => format!("{:?} -> {:?}", row.oldDevTrait, row.newDevTrait),
+        Status::AgeDecline => format!(
+            "{:?} -> {:?} (age decline)",
+            row.oldDevTrait, row.newDevTrait
+        ),
+        status => format!("{:?} ({status})", row.oldDevTrait),
+    }
+}
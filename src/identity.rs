@@ -0,0 +1,221 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+use itertools::Itertools;
+
+use crate::data::PlayerData;
+
+/// How sure we are that a player in the new export is the same person as one
+/// in the old export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Fewer than two details agree besides the name.
+    Low,
+    /// Two details agree besides the name.
+    Medium,
+    /// Three or more details agree besides the name.
+    High,
+    /// Same rosterId and name in both exports.
+    RosterId,
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Confidence::Low => write!(f, "low confidence"),
+            Confidence::Medium => write!(f, "medium confidence"),
+            Confidence::High => write!(f, "high confidence"),
+            Confidence::RosterId => write!(f, "rosterId"),
+        }
+    }
+}
+
+pub struct PlayerMatch<'a> {
+    pub old: &'a PlayerData,
+    pub confidence: Confidence,
+    /// Which details agreed, for anything not matched by rosterId.
+    pub evidence: Vec<&'static str>,
+}
+
+/// Pairs up players between the old and new exports. Normally this is just
+/// rosterId, but Madden or Neon can reassign ids (e.g. after a league
+/// re-sync), so anyone whose id doesn't line up is matched by name plus
+/// whatever else both exports have.
+pub struct Identities<'a> {
+    /// Keyed by rosterId in the new export.
+    matches: HashMap<u32, PlayerMatch<'a>>,
+    pub unmatched_old: Vec<&'a PlayerData>,
+    pub unmatched_new: Vec<&'a PlayerData>,
+}
+
+impl<'a> Identities<'a> {
+    pub fn new(players_old: &'a [PlayerData], players_new: &'a [PlayerData]) -> Self {
+        let old_by_id: HashMap<u32, &PlayerData> = players_old
+            .iter()
+            .map(|player| (player.rosterId, player))
+            .collect();
+        let mut matches = HashMap::new();
+        let mut unmatched_new = Vec::new();
+        for new in players_new {
+            match old_by_id.get(&new.rosterId) {
+                // A different name under the same id means the id was reused
+                Some(old) if old.fullName == new.fullName => {
+                    matches.insert(
+                        new.rosterId,
+                        PlayerMatch {
+                            old,
+                            confidence: Confidence::RosterId,
+                            evidence: Vec::new(),
+                        },
+                    );
+                }
+                _ => unmatched_new.push(new),
+            }
+        }
+        let matched_old: HashSet<u32> = matches
+            .values()
+            .map(|found: &PlayerMatch| found.old.rosterId)
+            .collect();
+        let mut unmatched_old = players_old
+            .iter()
+            .filter(|old| !matched_old.contains(&old.rosterId))
+            .collect_vec();
+
+        unmatched_new.retain(|new| {
+            let candidates = unmatched_old
+                .iter()
+                .filter(|old| old.fullName == new.fullName)
+                .map(|old| (*old, shared_details(old, new)))
+                .sorted_by_key(|(_, evidence)| Reverse(evidence.len()))
+                .collect_vec();
+            let Some((old, evidence)) = candidates.first() else {
+                return true;
+            };
+            // Two players with the same name and nothing to tell them apart
+            if candidates
+                .get(1)
+                .map(|(_, other)| other.len() == evidence.len())
+                .unwrap_or(false)
+            {
+                return true;
+            }
+            let old = *old;
+            unmatched_old.retain(|other| other.rosterId != old.rosterId);
+            matches.insert(
+                new.rosterId,
+                PlayerMatch {
+                    old,
+                    confidence: match evidence.len() {
+                        0 | 1 => Confidence::Low,
+                        2 => Confidence::Medium,
+                        _ => Confidence::High,
+                    },
+                    evidence: evidence.clone(),
+                },
+            );
+            false
+        });
+
+        Self {
+            matches,
+            unmatched_old,
+            unmatched_new,
+        }
+    }
+
+    /// The same player in the old export.
+    pub fn old(&self, new: &PlayerData) -> Option<&'a PlayerData> {
+        self.matches.get(&new.rosterId).map(|found| found.old)
+    }
+
+    /// Old rosterId -> new rosterId, for every matched player, whether or not
+    /// their id changed.
+    pub fn matched_ids(&self) -> HashMap<u32, u32> {
+        self.matches
+            .iter()
+            .map(|(new, found)| (found.old.rosterId, *new))
+            .collect()
+    }
+
+    /// Lists anyone who wasn't matched by rosterId, so a bad match can be
+    /// caught before it protects or demotes the wrong player. High confidence
+    /// matches are only counted, since a re-sync can change every id at once.
+    pub fn report(&self, players_new: &[PlayerData]) {
+        let counts = self.matches.values().counts_by(|found| found.confidence);
+        let by_name = counts
+            .iter()
+            .filter(|(confidence, _)| **confidence != Confidence::RosterId)
+            .map(|(_, count)| count)
+            .sum::<usize>();
+        if by_name > 0 {
+            eprintln!(
+                "Matched {by_name} players by name after their rosterId changed ({} high, {} \
+                 medium, {} low confidence)",
+                counts.get(&Confidence::High).unwrap_or(&0),
+                counts.get(&Confidence::Medium).unwrap_or(&0),
+                counts.get(&Confidence::Low).unwrap_or(&0)
+            );
+        }
+        for new in players_new {
+            let Some(found) = self.matches.get(&new.rosterId) else {
+                continue;
+            };
+            if found.confidence >= Confidence::High {
+                continue;
+            }
+            eprintln!(
+                "Matched {} by name ({}, rosterId {} -> {}): {}",
+                new.fullName,
+                found.confidence,
+                found.old.rosterId,
+                new.rosterId,
+                if found.evidence.is_empty() {
+                    "nothing else to compare".to_string()
+                } else {
+                    found.evidence.join(", ")
+                }
+            );
+        }
+        for old in &self.unmatched_old {
+            eprintln!(
+                "Warning: {} ({}) is in the old players export but couldn't be matched to \
+                 anyone in the new one",
+                old.fullName, old.rosterId
+            );
+        }
+        for new in &self.unmatched_new {
+            eprintln!(
+                "Warning: {} ({}) is in the new players export but couldn't be matched to \
+                 anyone in the old one",
+                new.fullName, new.rosterId
+            );
+        }
+    }
+}
+
+/// Details that are in both exports and agree. Anything missing from either
+/// one doesn't count for or against the match.
+fn shared_details(old: &PlayerData, new: &PlayerData) -> Vec<&'static str> {
+    let birth_date =
+        |player: &PlayerData| Some((player.birthYear?, player.birthMonth?, player.birthDay?));
+    [
+        ("birth date", agrees(birth_date(old), birth_date(new))),
+        (
+            "college",
+            agrees(old.college.as_ref(), new.college.as_ref()),
+        ),
+        ("rookie year", agrees(old.rookieYear, new.rookieYear)),
+        ("jersey", agrees(old.jerseyNum, new.jerseyNum)),
+    ]
+    .into_iter()
+    .filter(|(_, agrees)| *agrees)
+    .map(|(detail, _)| detail)
+    .collect()
+}
+
+fn agrees<T: PartialEq>(old: Option<T>, new: Option<T>) -> bool {
+    matches!((old, new), (Some(old), Some(new)) if old == new)
+}
@@ -1,141 +1,52 @@
 #![allow(non_snake_case)]
 
+mod bubble;
+mod changes;
+mod config;
+mod data;
+mod diff;
+mod expr;
+mod history;
+mod identity;
+mod positions;
+mod scoring;
+
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
-    env::args,
-    fs::{read_to_string, File},
-    io::BufReader,
-    mem::transmute,
+    cmp::Ordering, collections::HashMap, env::args, fs::read_to_string, io::stdout, mem::transmute,
+    ptr,
 };
 
-use csv::Reader;
+use csv::Writer;
 use itertools::Itertools;
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-const QB_LIMITS: DevLimits = DevLimits {
-    xf_min: 3,
-    xf_max: 4,
-    ss_min: 6,
-    ss_max: 8,
-    star_min: 12,
-    star_max: 20,
-};
-const HB_LIMITS: DevLimits = DevLimits {
-    xf_min: 2,
-    xf_max: 4,
-    ss_min: 8,
-    ss_max: 12,
-    star_min: 30,
-    star_max: 40,
-};
-const FB_LIMITS: DevLimits = DevLimits {
-    xf_min: 0,
-    xf_max: 0,
-    ss_min: 0,
-    ss_max: 2,
-    star_min: 3,
-    star_max: 5,
-};
-const WR_LIMITS: DevLimits = DevLimits {
-    xf_min: 8,
-    xf_max: 10,
-    ss_min: 12,
-    ss_max: 18,
-    star_min: 45,
-    star_max: 60,
-};
-const TE_LIMITS: DevLimits = DevLimits {
-    xf_min: 2,
-    xf_max: 4,
-    ss_min: 6,
-    ss_max: 8,
-    star_min: 24,
-    star_max: 32,
-};
-const OL_LIMITS: DevLimits = DevLimits {
-    xf_min: 0,
-    xf_max: 0,
-    ss_min: 12,
-    ss_max: 18,
-    star_min: 75,
-    star_max: 90,
-};
-const IDL_LIMITS: DevLimits = DevLimits {
-    xf_min: 4,
-    xf_max: 6,
-    ss_min: 10,
-    ss_max: 12,
-    star_min: 36,
-    star_max: 50,
-};
-const EDGE_LIMITS: DevLimits = DevLimits {
-    xf_min: 4,
-    xf_max: 6,
-    ss_min: 8,
-    ss_max: 12,
-    star_min: 25,
-    star_max: 40,
-};
-const LB_LIMITS: DevLimits = DevLimits {
-    xf_min: 2,
-    xf_max: 4,
-    ss_min: 6,
-    ss_max: 10,
-    star_min: 24,
-    star_max: 36,
-};
-const CB_LIMITS: DevLimits = DevLimits {
-    xf_min: 3,
-    xf_max: 6,
-    ss_min: 10,
-    ss_max: 16,
-    star_min: 45,
-    star_max: 60,
-};
-const S_LIMITS: DevLimits = DevLimits {
-    xf_min: 3,
-    xf_max: 5,
-    ss_min: 8,
-    ss_max: 12,
-    star_min: 36,
-    star_max: 50,
-};
-const K_LIMITS: DevLimits = DevLimits {
-    xf_min: 0,
-    xf_max: 0,
-    ss_min: 1,
-    ss_max: 3,
-    star_min: 5,
-    star_max: 8,
-};
-const P_LIMITS: DevLimits = DevLimits {
-    xf_min: 0,
-    xf_max: 0,
-    ss_min: 1,
-    ss_max: 2,
-    star_min: 3,
-    star_max: 6,
+use crate::{
+    changes::{key, Changes, Status},
+    config::{BudgetOrder, Config, DevLimits, FreeAgentPolicy, PositionChangePolicy},
+    data::{extra_player_columns, read_players, team_name, PlayerData, SeasonStats},
+    diff::{diff, ChangeKind, SnapshotChange},
+    history::{SeasonRecord, HISTORY_PATH},
+    identity::Identities,
+    positions::{MaddenVersion, PositionCatalog},
+    scoring::{
+        apply_scoring_mode, compare_across_groups, rank_group, rolling_score, ScoredPlayer,
+        ScorerRegistry,
+    },
 };
 
-const THREE_FOUR_TEAMS: &[&str] = &[
-    "Broncos",
-    "Browns",
-    "Buccaneers",
-    "Cardinals",
-    "Cowboys",
-    "Dolphins",
-    "Giants",
-    "Jaguars",
-    "Packers",
-    "Patriots",
-    "Texans",
-    "Titans",
-];
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    /// Just the changes, grouped by team, ready to post for the league.
+    Report,
+    /// Every player's score and status, grouped by position.
+    Debug,
+    /// Same as debug, but as CSV for other tools.
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 enum DevTrait {
     XFactor = 3,
@@ -145,7 +56,28 @@ enum DevTrait {
 }
 
 fn main() {
-    let debug = args().nth(1) == Some("--debug".to_string());
+    let args = args().skip(1).collect_vec();
+    match args.first().map(String::as_str) {
+        Some("history") => {
+            history::query(&args[1..]);
+            return;
+        }
+        Some("diff") => {
+            diff::run(&args[1..]);
+            return;
+        }
+        _ => {}
+    }
+    let output = if args.iter().any(|arg| arg == "--debug") {
+        Output::Debug
+    } else if args.iter().any(|arg| arg == "--csv") {
+        Output::Csv
+    } else {
+        Output::Report
+    };
+    let record = args.iter().any(|arg| arg == "--record");
+    let bubble_watch = args.iter().any(|arg| arg == "--bubble");
+    let config = Config::load();
 
     // We want the randomness to be deterministic for the same season in the same
     // league, but have variance for different seasons and for different
@@ -163,177 +95,153 @@ fn main() {
         .collect::<String>();
     hasher.update(players_raw_data.as_bytes());
     let result = hasher.finalize();
+    let inputs_hash = format!("{result:x}");
+    if record {
+        history::check_not_recorded(&inputs_hash);
+    }
 
     let mut seed = [0u8; 32];
     seed.copy_from_slice(&result);
     let mut rng = StdRng::from_seed(seed);
 
-    let total_xf_max = QB_LIMITS.xf_max
-        + HB_LIMITS.xf_max
-        + FB_LIMITS.xf_max
-        + WR_LIMITS.xf_max
-        + TE_LIMITS.xf_max
-        + OL_LIMITS.xf_max
-        + IDL_LIMITS.xf_max
-        + EDGE_LIMITS.xf_max
-        + LB_LIMITS.xf_max
-        + CB_LIMITS.xf_max
-        + S_LIMITS.xf_max
-        + K_LIMITS.xf_max
-        + P_LIMITS.xf_max;
-    let total_xf_min = QB_LIMITS.xf_min
-        + HB_LIMITS.xf_min
-        + FB_LIMITS.xf_min
-        + WR_LIMITS.xf_min
-        + TE_LIMITS.xf_min
-        + OL_LIMITS.xf_min
-        + IDL_LIMITS.xf_min
-        + EDGE_LIMITS.xf_min
-        + LB_LIMITS.xf_min
-        + CB_LIMITS.xf_min
-        + S_LIMITS.xf_min
-        + K_LIMITS.xf_min
-        + P_LIMITS.xf_min;
-    let total_ss_max = QB_LIMITS.ss_max
-        + HB_LIMITS.ss_max
-        + FB_LIMITS.ss_max
-        + WR_LIMITS.ss_max
-        + TE_LIMITS.ss_max
-        + OL_LIMITS.ss_max
-        + IDL_LIMITS.ss_max
-        + EDGE_LIMITS.ss_max
-        + LB_LIMITS.ss_max
-        + CB_LIMITS.ss_max
-        + S_LIMITS.ss_max
-        + K_LIMITS.ss_max
-        + P_LIMITS.ss_max
-        - total_xf_max;
-    let total_ss_min = QB_LIMITS.ss_min
-        + HB_LIMITS.ss_min
-        + FB_LIMITS.ss_min
-        + WR_LIMITS.ss_min
-        + TE_LIMITS.ss_min
-        + OL_LIMITS.ss_min
-        + IDL_LIMITS.ss_min
-        + EDGE_LIMITS.ss_min
-        + LB_LIMITS.ss_min
-        + CB_LIMITS.ss_min
-        + S_LIMITS.ss_min
-        + K_LIMITS.ss_min
-        + P_LIMITS.ss_min
-        - total_xf_min;
-    let total_star_max = QB_LIMITS.star_max
-        + HB_LIMITS.star_max
-        + FB_LIMITS.star_max
-        + WR_LIMITS.star_max
-        + TE_LIMITS.star_max
-        + OL_LIMITS.star_max
-        + IDL_LIMITS.star_max
-        + EDGE_LIMITS.star_max
-        + LB_LIMITS.star_max
-        + CB_LIMITS.star_max
-        + S_LIMITS.star_max
-        + K_LIMITS.star_max
-        + P_LIMITS.star_max
-        - total_ss_max;
-    let total_star_min = QB_LIMITS.star_min
-        + HB_LIMITS.star_min
-        + FB_LIMITS.star_min
-        + WR_LIMITS.star_min
-        + TE_LIMITS.star_min
-        + OL_LIMITS.star_min
-        + IDL_LIMITS.star_min
-        + EDGE_LIMITS.star_min
-        + LB_LIMITS.star_min
-        + CB_LIMITS.star_min
-        + S_LIMITS.star_min
-        + K_LIMITS.star_min
-        + P_LIMITS.star_min
-        - total_ss_min;
-    println!("Overall targets:");
-    println!("XF: {total_xf_min}-{total_xf_max}");
-    println!("SS: {total_ss_min}-{total_ss_max}");
-    println!("Star: {total_star_min}-{total_star_max}");
-    println!();
-
-    let passing_stats: Vec<PassingData> = read_csv("data/neon_season/SFDL_passing.csv");
-    let receiving_stats: Vec<ReceivingData> = read_csv("data/neon_season/SFDL_receiving.csv");
-    let rushing_stats: Vec<RushingData> = read_csv("data/neon_season/SFDL_rushing.csv");
-    let defense_stats: Vec<DefenseData> = read_csv("data/neon_season/SFDL_defense.csv");
-    let kicking_stats: Vec<KickingData> = read_csv("data/neon_season/SFDL_kicking.csv");
-    let punting_stats: Vec<PuntingData> = read_csv("data/neon_season/SFDL_punting.csv");
-    let players_old: Vec<PlayerData> = read_csv("data/neon_players_old/SFDL_players.csv");
-    let players_new: Vec<PlayerData> = read_csv("data/neon_players_new/SFDL_players.csv");
-
-    // List of names
-    let mut protected_players = HashSet::new();
-    let mut upgraded_players = HashSet::new();
-    // Map<(Name, Team, Position), (OldDev, NewDev)>
-    let mut changed_players: HashMap<(String, String, String), (DevTrait, DevTrait)> =
-        HashMap::new();
-    for (pos, limits) in [
-        ("QB", QB_LIMITS),
-        ("HB", HB_LIMITS),
-        ("FB", FB_LIMITS),
-        ("WR", WR_LIMITS),
-        ("TE", TE_LIMITS),
-        ("OL", OL_LIMITS),
-        ("IDL", IDL_LIMITS),
-        ("EDGE", EDGE_LIMITS),
-        ("LB", LB_LIMITS),
-        ("CB", CB_LIMITS),
-        ("S", S_LIMITS),
-        ("K", K_LIMITS),
-        ("P", P_LIMITS),
-    ] {
-        // All players at the position who can be considered for demotion
-        let players = if pos == "IDL" {
-            players_new
-                .iter()
-                .filter(|player| {
-                    player.position == "DT"
-                        || (["LE", "RE"].contains(&player.position.as_str())
-                            && THREE_FOUR_TEAMS.contains(&player.team.as_str()))
-                })
-                .collect_vec()
-        } else if pos == "EDGE" {
-            players_new
-                .iter()
-                .filter(|player| {
-                    (["LE", "RE"].contains(&player.position.as_str())
-                        && !THREE_FOUR_TEAMS.contains(&player.team.as_str()))
-                        || (["LOLB", "ROLB"].contains(&player.position.as_str())
-                            && THREE_FOUR_TEAMS.contains(&player.team.as_str()))
-                })
-                .collect_vec()
-        } else if pos == "LB" {
-            players_new
-                .iter()
-                .filter(|player| {
-                    (["LOLB", "ROLB"].contains(&player.position.as_str())
-                        && !THREE_FOUR_TEAMS.contains(&player.team.as_str()))
-                        || player.position == "MLB"
-                })
-                .collect_vec()
-        } else if pos == "S" {
-            players_new
-                .iter()
-                .filter(|player| ["FS", "SS"].contains(&player.position.as_str()))
-                .collect_vec()
-        } else if pos == "OL" {
-            players_new
-                .iter()
-                .filter(|player| ["LG", "LT", "RG", "RT", "C"].contains(&player.position.as_str()))
-                .collect_vec()
-        } else {
-            players_new
-                .iter()
-                .filter(|player| player.position == pos)
-                .collect_vec()
-        }
-        .into_iter()
-        .filter(|player| !player.isRetired)
+    let players_old = read_players("data/neon_players_old/SFDL_players.csv");
+    let players_new = read_players("data/neon_players_new/SFDL_players.csv");
+    let scorers = ScorerRegistry::from_config(&config, &extra_player_columns(&players_new));
+    let positions = PositionCatalog::new(MaddenVersion::detect(&players_new), &config);
+    if output == Output::Debug {
+        println!("Position names: {}", positions.version);
+        println!();
+    }
+    let identities = Identities::new(&players_old, &players_new);
+    identities.report(&players_new);
+    let matched_ids = identities.matched_ids();
+    let mut season_stats = SeasonStats::load("data/neon_season");
+    season_stats.remap_ids(&matched_ids);
+    let prior_stats = config
+        .prior_seasons
+        .iter()
+        .map(|season| {
+            let mut stats = SeasonStats::load(&format!("data/{}", season.dir));
+            stats.remap_ids(&matched_ids);
+            (stats, season.weight)
+        })
         .collect_vec();
+    // Map<New rosterId, Change> for players who moved positions in the offseason
+    let position_changes: HashMap<u32, SnapshotChange> = diff(&players_new, &identities)
+        .into_iter()
+        .filter(|change| change.change == ChangeKind::Position)
+        .map(|change| (change.rosterId, change))
+        .collect();
+    let old_position = |player: &PlayerData| {
+        position_changes
+            .get(&player.rosterId)
+            .map(|change| change.old.as_str())
+    };
+    // The group each player is judged in, depending on `position_changes`
+    let classify = |player: &PlayerData| match (config.position_changes, old_position(player)) {
+        (PositionChangePolicy::Season, Some(old)) => positions.classify_as(player, old),
+        _ => positions.classify(player),
+    };
+    let group_reason = |player: &PlayerData| {
+        let reason = classify(player).unwrap().reason;
+        match (config.position_changes, old_position(player)) {
+            (_, None) => reason,
+            (PositionChangePolicy::Season, Some(_)) => format!("{reason}, now {}", player.position),
+            (_, Some(old)) => format!("{reason}, moved from {old}"),
+        }
+    };
+    for player in players_new
+        .iter()
+        .filter(|player| classify(player).is_none())
+    {
+        eprintln!(
+            "Warning: {} has a position of {}, which isn't known for {}, so they will be \
+             skipped",
+            player.fullName, player.position, positions.version
+        );
+    }
+
+    let groups = positions.groups();
+    let total = |limit: fn(&DevLimits) -> usize| -> usize {
+        groups
+            .iter()
+            .map(|group| limit(&config.limits(group)))
+            .sum()
+    };
+    let total_xf_max = total(|l| l.xf_max);
+    let total_xf_min = total(|l| l.xf_min);
+    let total_ss_max = total(|l| l.ss_max) - total_xf_max;
+    let total_ss_min = total(|l| l.ss_min) - total_xf_min;
+    let total_star_max = total(|l| l.star_max) - total_ss_max;
+    let total_star_min = total(|l| l.star_min) - total_ss_min;
+    if output != Output::Csv {
+        println!("Overall targets:");
+        println!("XF: {total_xf_min}-{total_xf_max}");
+        println!("SS: {total_ss_min}-{total_ss_max}");
+        println!("Star: {total_star_min}-{total_star_max}");
+        println!();
+    }
+
+    // Seasons in a row each player has been warned, going back from the last
+    // recorded season
+    let strikes = if config.demotion_strikes > 1 {
+        history::strikes(&history::load(), &inputs_hash)
+    } else {
+        HashMap::new()
+    };
+    let mut changes = Changes::new(strikes, config.demotion_strikes);
+    // Why a player can't be promoted to fill a minimum, if they can't
+    let promotion_block = |player: &PlayerData, pos: &str| -> Option<String> {
+        let rules = &config.promotion_rules;
+        if (player.team.is_empty() && config.free_agents == FreeAgentPolicy::ExcludePromotions)
+            || (rules.rostered_only && (player.team.is_empty() || player.isFreeAgent == Some(true)))
+        {
+            return Some("free agent".to_string());
+        }
+        if rules.rostered_only && player.isOnPracticeSquad == Some(true) {
+            return Some("practice squad".to_string());
+        }
+        if let Some(min_ovr) = rules.min_ovr {
+            if player.playerBestOvr < min_ovr {
+                return Some(format!("OVR {} is below {min_ovr}", player.playerBestOvr));
+            }
+        }
+        if let Some(max_age) = rules.max_age {
+            if player.age > max_age {
+                return Some(format!("age {} is above {max_age}", player.age));
+            }
+        }
+        // We don't have games played stats for OL
+        if let (Some(min_games), false) = (rules.min_games, pos == "OL") {
+            let games = season_stats.games_played(player.rosterId);
+            if games < min_games {
+                return Some(format!("played {games} games, fewer than {min_games}"));
+            }
+        }
+        None
+    };
+    // Every group's players, ranked, in the order they're printed
+    let mut ranked_groups = Vec::new();
+    // How free agents affected each group's quota
+    let mut free_agent_summary = Vec::new();
+    // Promotions and demotions where the cutoff fell between two players with the
+    // same score
+    let mut cutoff_ties = Vec::new();
+    // Candidates who would have been promoted if not for the promotion rules
+    let mut skipped_promotions = Vec::new();
+    // Players picked for demotion who were within the grace band of the cutoff
+    let mut on_the_bubble = Vec::new();
+    // Holders replaced by a better player just below them
+    let mut merit_swaps = Vec::new();
+    let mut score_rows = Vec::new();
+    for &pos in &groups {
+        let limits = config.limits(pos);
+        // All players at the position who can be considered for demotion
+        let players = players_new
+            .iter()
+            .filter(|player| classify(player).map(|c| c.group) == Some(pos))
+            .filter(|player| !player.isRetired)
+            .collect_vec();
 
         // Protect rookies who played at least 8 games
         for player in players
@@ -344,308 +252,613 @@ fn main() {
                     // We don't have game played stats for OL so we need to protect all OL rookies.
                     return true;
                 }
-                let games_played = passing_stats
-                    .iter()
-                    .filter(|stat| stat.player__rosterId == player.rosterId)
-                    .map(|stat| stat.gamesPlayed)
-                    .sum::<u8>()
-                    .max(
-                        rushing_stats
-                            .iter()
-                            .filter(|stat| stat.player__rosterId == player.rosterId)
-                            .map(|stat| stat.gamesPlayed)
-                            .sum(),
-                    )
-                    .max(
-                        receiving_stats
-                            .iter()
-                            .filter(|stat| stat.player__rosterId == player.rosterId)
-                            .map(|stat| stat.gamesPlayed)
-                            .sum(),
-                    )
-                    .max(
-                        defense_stats
-                            .iter()
-                            .filter(|stat| stat.player__rosterId == player.rosterId)
-                            .map(|stat| stat.gamesPlayed)
-                            .sum(),
-                    )
-                    .max(
-                        kicking_stats
-                            .iter()
-                            .filter(|stat| stat.player__rosterId == player.rosterId)
-                            .map(|stat| stat.gamesPlayed)
-                            .sum(),
-                    )
-                    .max(
-                        punting_stats
-                            .iter()
-                            .filter(|stat| stat.player__rosterId == player.rosterId)
-                            .map(|stat| stat.gamesPlayed)
-                            .sum(),
-                    );
-                games_played >= 8
+                season_stats.games_played(player.rosterId) >= 8
             })
         {
-            protected_players.insert(player.fullName.clone());
+            changes.protect(player);
         }
 
-        // Protect players who just devved up
-        for player in players_old.iter().filter(|player| {
-            players_new
+        // Protect players who changed positions, if configured
+        if config.position_changes == PositionChangePolicy::Exempt {
+            for player in players
                 .iter()
-                .find(|new| new.rosterId == player.rosterId)
-                .map(|new| new.devTrait > player.devTrait)
+                .filter(|player| position_changes.contains_key(&player.rosterId))
+            {
+                changes.protect(player);
+            }
+        }
+
+        // Protect players who just devved up
+        for player in players_new.iter().filter(|player| {
+            identities
+                .old(player)
+                .map(|old| player.devTrait > old.devTrait)
                 .unwrap_or(false)
         }) {
-            protected_players.insert(player.fullName.clone());
-            upgraded_players.insert(player.fullName.clone());
+            changes.devved_up(player);
         }
 
-        // Sort players according to their performance this season
-        let players = players
-            .into_iter()
-            .map(|player| {
-                (
-                    player,
-                    match pos {
-                        "QB" => {
-                            let pass_stats = passing_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-                            let rush_stats = rushing_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-
-                            calc_qb_score(player, pass_stats, rush_stats)
-                        }
-                        "HB" | "FB" | "WR" | "TE" => {
-                            let recv_stats = receiving_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-                            let rush_stats = rushing_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-
-                            calc_receiver_score(player, recv_stats, rush_stats)
-                        }
-                        "OL" => calc_ol_score(player, &mut rng),
-                        "IDL" | "EDGE" | "LB" | "CB" | "S" => {
-                            let stats = defense_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-
-                            calc_defense_score(player, stats)
-                        }
-                        "K" => {
-                            let stats = kicking_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
-
-                            calc_kicker_score(player, stats)
-                        }
-                        "P" => {
-                            let stats = punting_stats
-                                .iter()
-                                .find(|stat| stat.player__rosterId == player.rosterId);
+        // Sort players according to their performance this season, and earlier
+        // ones if configured
+        let players = rank_group(
+            players
+                .into_iter()
+                .map(|player| {
+                    let mut season_score = |stats: &SeasonStats| {
+                        let score = scorers.get(pos).score(player, stats, &mut rng);
+                        apply_scoring_mode(
+                            score,
+                            stats.games_played(player.rosterId),
+                            config.scoring_mode(pos),
+                            config.season_games,
+                        )
+                    };
+                    let current = season_score(&season_stats);
+                    let prior = prior_stats
+                        .iter()
+                        .filter(|(stats, _)| stats.games_played(player.rosterId) > 0)
+                        .map(|(stats, weight)| (season_score(stats), *weight))
+                        .collect_vec();
+                    let score = rolling_score(current, &prior);
+                    if score.is_nan() {
+                        panic!(
+                            "{} ({pos}) has a score of NaN, check the scoring config for {pos}",
+                            player.fullName
+                        );
+                    }
+                    (player, current, score)
+                })
+                .collect_vec(),
+            config.rank_by,
+            config.ovr_blend(pos),
+        );
 
-                            calc_punter_score(player, stats)
-                        }
-                        _ => unreachable!(),
-                    },
-                )
-            })
-            .sorted_unstable_by(|(_, score_a), (_, score_b)| {
-                if score_a > score_b {
-                    Ordering::Less
-                } else if score_b > score_a {
-                    Ordering::Greater
-                } else {
-                    Ordering::Equal
-                }
-            })
+        // The pools that quotas are applied to. Normally that's the whole group,
+        // but free agents can be left out or given a quota of their own.
+        let free_agents = players
+            .iter()
+            .filter(|ScoredPlayer { player, .. }| player.team.is_empty())
+            .copied()
             .collect_vec();
-
-        let star_count = players
+        let rostered = players
             .iter()
-            .filter(|(x, _)| x.devTrait >= DevTrait::Star as u8)
-            .count();
-        if star_count < limits.star_min {
-            let players = players
-                .iter()
-                .filter(|(player, _)| player.devTrait == DevTrait::Normal as u8)
-                .take(limits.star_min - star_count)
-                .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                upgraded_players.insert(player.fullName.clone());
-                changed_players.insert(
+            .filter(|ScoredPlayer { player, .. }| !player.team.is_empty())
+            .copied()
+            .collect_vec();
+        let pools = match config.free_agents {
+            FreeAgentPolicy::Include | FreeAgentPolicy::ExcludePromotions => {
+                vec![("", players.clone(), limits)]
+            }
+            FreeAgentPolicy::ExcludeCounts => vec![("", rostered, limits)],
+            FreeAgentPolicy::Separate => {
+                let free_agent_limits =
+                    limits.scaled(free_agents.len() as f32 / players.len() as f32);
+                vec![
+                    ("", rostered, limits.minus(&free_agent_limits)),
                     (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
+                        " free agent",
+                        free_agents.clone(),
+                        free_agent_limits.maximums(),
                     ),
-                    (DevTrait::Normal, DevTrait::Star),
-                );
+                ]
             }
-        } else if star_count > limits.star_max {
-            let players = players
+        };
+        free_agent_summary.extend(describe_free_agents(
+            pos,
+            &free_agents,
+            config.free_agents,
+            pools.get(1).map(|(_, _, limits)| limits),
+        ));
+
+        let mut swap_count = 0;
+        for (pool, players, limits) in pools {
+            let star_count = players
                 .iter()
-                .filter(|(player, _)| {
-                    player.devTrait >= DevTrait::Star as u8
-                        && !protected_players.contains(&player.fullName)
-                })
-                .rev()
-                .take(star_count - limits.star_max)
-                .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                changed_players.insert(
-                    (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    ),
-                    (
-                        unsafe { transmute::<_, DevTrait>(player.devTrait) },
-                        unsafe { transmute::<_, DevTrait>(player.devTrait - 1) },
-                    ),
+                .filter(|ScoredPlayer { player: x, .. }| x.devTrait >= DevTrait::Star as u8)
+                .count();
+            if star_count < limits.star_min {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| player.devTrait == DevTrait::Normal as u8)
+                    .collect_vec();
+                skipped_promotions.extend(
+                    skipped_candidates(&candidates, limits.star_min - star_count, |player| {
+                        promotion_block(player, pos)
+                    })
+                    .into_iter()
+                    .map(|(player, reason)| {
+                        format!(
+                            "{pos}{pool} Star promotion: {} ({}), {reason}",
+                            player.fullName,
+                            team_name(&player.team)
+                        )
+                    }),
+                );
+                let (players, tie) = take_with_cutoff(
+                    candidates
+                        .iter()
+                        .copied()
+                        .filter(|ScoredPlayer { player, .. }| {
+                            promotion_block(player, pos).is_none()
+                        }),
+                    limits.star_min - star_count,
+                );
+                if let Some((picked, skipped, score)) = tie {
+                    cutoff_ties.push(format!(
+                        "{pos}{pool} Star promotion: {} and {} both scored {score:.2}, {} was picked by the \
+                         tie-breakers",
+                        picked.fullName, skipped.fullName, picked.fullName
+                    ));
+                }
+                for ScoredPlayer { player, .. } in players {
+                    changes.promote(player, DevTrait::Star);
+                }
+            } else if star_count > limits.star_max {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait >= DevTrait::Star as u8 && !changes.is_protected(player)
+                    })
+                    .rev()
+                    .collect_vec();
+                demote_overflow(
+                    &format!("{pos}{pool} Star demotion"),
+                    &candidates,
+                    star_count - limits.star_max,
+                    config.grace_band,
+                    &mut changes,
+                    &mut on_the_bubble,
+                    &mut cutoff_ties,
                 );
             }
-        }
 
-        let ss_count = players
-            .iter()
-            .filter(|(x, _)| x.devTrait >= DevTrait::Superstar as u8)
-            .count();
-        if ss_count < limits.ss_min {
-            let players = players
+            let ss_count = players
                 .iter()
-                .filter(|(player, _)| {
-                    player.devTrait == DevTrait::Star as u8
-                        && !upgraded_players.contains(&player.fullName)
-                })
-                .take(limits.ss_min - ss_count)
-                .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                upgraded_players.insert(player.fullName.clone());
-                changed_players.insert(
-                    (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    ),
-                    (DevTrait::Star, DevTrait::Superstar),
+                .filter(|ScoredPlayer { player: x, .. }| x.devTrait >= DevTrait::Superstar as u8)
+                .count();
+            if ss_count < limits.ss_min {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait == DevTrait::Star as u8 && !changes.is_upgraded(player)
+                    })
+                    .collect_vec();
+                skipped_promotions.extend(
+                    skipped_candidates(&candidates, limits.ss_min - ss_count, |player| {
+                        promotion_block(player, pos)
+                    })
+                    .into_iter()
+                    .map(|(player, reason)| {
+                        format!(
+                            "{pos}{pool} Superstar promotion: {} ({}), {reason}",
+                            player.fullName,
+                            team_name(&player.team)
+                        )
+                    }),
+                );
+                let (players, tie) = take_with_cutoff(
+                    candidates
+                        .iter()
+                        .copied()
+                        .filter(|ScoredPlayer { player, .. }| {
+                            promotion_block(player, pos).is_none()
+                        }),
+                    limits.ss_min - ss_count,
+                );
+                if let Some((picked, skipped, score)) = tie {
+                    cutoff_ties.push(format!(
+                        "{pos}{pool} Superstar promotion: {} and {} both scored {score:.2}, {} was picked by the \
+                         tie-breakers",
+                        picked.fullName, skipped.fullName, picked.fullName
+                    ));
+                }
+                for ScoredPlayer { player, .. } in players {
+                    changes.promote(player, DevTrait::Superstar);
+                }
+            } else if ss_count > limits.ss_max {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait >= DevTrait::Superstar as u8
+                            && !changes.is_protected(player)
+                    })
+                    .rev()
+                    .collect_vec();
+                demote_overflow(
+                    &format!("{pos}{pool} Superstar demotion"),
+                    &candidates,
+                    ss_count - limits.ss_max,
+                    config.grace_band,
+                    &mut changes,
+                    &mut on_the_bubble,
+                    &mut cutoff_ties,
                 );
             }
-        } else if ss_count > limits.ss_max {
-            let players = players
+
+            let xf_count = players
                 .iter()
-                .filter(|(player, _)| {
-                    player.devTrait >= DevTrait::Superstar as u8
-                        && !protected_players.contains(&player.fullName)
-                })
-                .rev()
-                .take(ss_count - limits.ss_max)
-                .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                changed_players.insert(
-                    (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    ),
-                    (
-                        unsafe { transmute::<_, DevTrait>(player.devTrait) },
-                        unsafe { transmute::<_, DevTrait>(player.devTrait - 1) },
-                    ),
+                .filter(|ScoredPlayer { player: x, .. }| x.devTrait >= DevTrait::XFactor as u8)
+                .count();
+            if xf_count < limits.xf_min {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait == DevTrait::Superstar as u8 && !changes.is_upgraded(player)
+                    })
+                    .collect_vec();
+                skipped_promotions.extend(
+                    skipped_candidates(&candidates, limits.xf_min - xf_count, |player| {
+                        promotion_block(player, pos)
+                    })
+                    .into_iter()
+                    .map(|(player, reason)| {
+                        format!(
+                            "{pos}{pool} XFactor promotion: {} ({}), {reason}",
+                            player.fullName,
+                            team_name(&player.team)
+                        )
+                    }),
                 );
+                let (players, tie) = take_with_cutoff(
+                    candidates
+                        .iter()
+                        .copied()
+                        .filter(|ScoredPlayer { player, .. }| {
+                            promotion_block(player, pos).is_none()
+                        }),
+                    limits.xf_min - xf_count,
+                );
+                if let Some((picked, skipped, score)) = tie {
+                    cutoff_ties.push(format!(
+                        "{pos}{pool} XFactor promotion: {} and {} both scored {score:.2}, {} was picked by the \
+                         tie-breakers",
+                        picked.fullName, skipped.fullName, picked.fullName
+                    ));
+                }
+                for ScoredPlayer { player, .. } in players {
+                    changes.promote(player, DevTrait::XFactor);
+                }
+            } else if xf_count > limits.xf_max {
+                let candidates = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait == DevTrait::XFactor as u8 && !changes.is_protected(player)
+                    })
+                    .rev()
+                    .collect_vec();
+                demote_overflow(
+                    &format!("{pos}{pool} XFactor demotion"),
+                    &candidates,
+                    xf_count - limits.xf_max,
+                    config.grace_band,
+                    &mut changes,
+                    &mut on_the_bubble,
+                    &mut cutoff_ties,
+                );
+            }
+
+            // Trade the weakest holder at each tier for the best player just below
+            // it, which leaves the counts where the quotas put them
+            let Some(swaps) = config.merit_swaps.as_ref() else {
+                continue;
+            };
+            for dev in [DevTrait::Star, DevTrait::Superstar, DevTrait::XFactor] {
+                let holders = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait == dev as u8 && !changes.is_protected(player)
+                    })
+                    .rev()
+                    .collect_vec();
+                let challengers = players
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| {
+                        player.devTrait == dev as u8 - 1
+                            && !changes.is_upgraded(player)
+                            && !changes.is_changed(player)
+                            && promotion_block(player, pos).is_none()
+                    })
+                    .collect_vec();
+                // A holder who's only warned doesn't free up their trait, so the
+                // challenger stays in line for the next one
+                let mut challengers = challengers.into_iter().peekable();
+                for holder in holders {
+                    if swap_count >= swaps.max_per_group {
+                        break;
+                    }
+                    let Some(challenger) = challengers.peek().copied() else {
+                        break;
+                    };
+                    // Both lists only get closer from here
+                    let gap = challenger.ranking_score - holder.ranking_score;
+                    if gap <= swaps.margin {
+                        break;
+                    }
+                    if !changes.demote(holder.player) {
+                        continue;
+                    }
+                    changes.promote(challenger.player, dev);
+                    challengers.next();
+                    swap_count += 1;
+                    merit_swaps.push(format!(
+                        "{pos}{pool} {dev:?}: {} ({:.2}) replaced {} ({:.2}), a gap of {gap:.2}",
+                        challenger.player.fullName,
+                        challenger.ranking_score,
+                        holder.player.fullName,
+                        holder.ranking_score
+                    ));
+                }
             }
         }
 
-        let xf_count = players
-            .iter()
-            .filter(|(x, _)| x.devTrait >= DevTrait::XFactor as u8)
-            .count();
-        if xf_count < limits.xf_min {
-            let players = players
+        ranked_groups.push((pos, players));
+    }
+
+    // Veterans step down a tier no matter where their group is against its
+    // quotas, unless they were one of its best players. Free agents left out of
+    // the quotas are never changed, so they don't take up a top spot either.
+    if let Some(decline) = config.age_decline.as_ref() {
+        for (_, players) in &ranked_groups {
+            for ScoredPlayer { player, .. } in players
                 .iter()
-                .filter(|(player, _)| {
-                    player.devTrait == DevTrait::Superstar as u8
-                        && !upgraded_players.contains(&player.fullName)
+                .filter(|ScoredPlayer { player, .. }| {
+                    config.free_agents != FreeAgentPolicy::ExcludeCounts || !player.team.is_empty()
                 })
-                .take(limits.xf_min - xf_count)
-                .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                upgraded_players.insert(player.fullName.clone());
-                changed_players.insert(
-                    (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    ),
-                    (DevTrait::Superstar, DevTrait::XFactor),
-                );
+                .skip(decline.top_n)
+            {
+                if player.age <= decline.age
+                    || player.devTrait < DevTrait::Superstar as u8
+                    || changes.is_protected(player)
+                {
+                    continue;
+                }
+                changes.age_decline(player);
             }
-        } else if xf_count > limits.xf_max {
-            let players = players
+        }
+    }
+
+    // Teams' players are spread across groups, so caps and budgets can only be
+    // enforced once every group has been through its quotas
+    let teams = ranked_groups
+        .iter()
+        .flat_map(|(_, players)| players)
+        .filter(|ScoredPlayer { player, .. }| !player.team.is_empty())
+        .into_group_map_by(|ScoredPlayer { player, .. }| player.team.clone())
+        .into_iter()
+        .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+        .collect_vec();
+    let mut team_cap_breaches = Vec::new();
+    if let Some(caps) = config.team_caps.as_ref() {
+        for (team, roster) in &teams {
+            for (dev, cap) in [
+                (DevTrait::XFactor, caps.xf),
+                (DevTrait::Superstar, caps.ss),
+                (DevTrait::Star, caps.star),
+            ] {
+                let Some(cap) = cap else {
+                    continue;
+                };
+                let count = roster
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| changes.current_dev(player) >= dev as u8)
+                    .count();
+                if count <= cap {
+                    continue;
+                }
+                // Only players right at this tier, since demoting anyone above it
+                // wouldn't bring the count down
+                let at_tier = roster
+                    .iter()
+                    .filter(|ScoredPlayer { player, .. }| changes.current_dev(player) == dev as u8)
+                    .collect_vec();
+                let above = count - at_tier.len();
+                let demoted = at_tier
+                    .into_iter()
+                    .filter(|ScoredPlayer { player, .. }| !changes.is_protected(player))
+                    .sorted_by(|a, b| compare_across_groups(b, a))
+                    .take(count - cap)
+                    .collect_vec();
+                for ScoredPlayer { player, .. } in &demoted {
+                    changes.force_demote(player, dev as u8);
+                }
+                team_cap_breaches.push(format!(
+                    "{team} had {count} {dev:?}{} with a cap of {cap}, demoted {}{}",
+                    if dev == DevTrait::XFactor {
+                        ""
+                    } else {
+                        " or better"
+                    },
+                    if demoted.is_empty() {
+                        "nobody".to_string()
+                    } else {
+                        demoted
+                            .iter()
+                            .map(|ScoredPlayer { player, .. }| {
+                                format!("{} {}", player.position, player.fullName)
+                            })
+                            .join(", ")
+                    },
+                    if demoted.len() < count - cap {
+                        format!(
+                            " (the rest of its {dev:?}s are protected{})",
+                            if above > 0 {
+                                format!(", and demoting its {above} better players wouldn't help")
+                            } else {
+                                String::new()
+                            }
+                        )
+                    } else {
+                        String::new()
+                    }
+                ));
+            }
+        }
+    }
+
+    // Caps go first, so a budget only has to make up whatever they didn't
+    let mut team_budget_breaches = Vec::new();
+    if let Some(budget) = config.team_budget.as_ref() {
+        let cost = |dev: u8| budget.costs.cost(unsafe { transmute::<u8, DevTrait>(dev) });
+        for (team, roster) in &teams {
+            let points = roster
                 .iter()
-                .filter(|(player, _)| {
-                    player.devTrait == DevTrait::XFactor as u8
-                        && !protected_players.contains(&player.fullName)
+                .map(|ScoredPlayer { player, .. }| cost(changes.current_dev(player)))
+                .sum::<usize>();
+            if points <= budget.points {
+                continue;
+            }
+            let candidates = roster
+                .iter()
+                .filter(|ScoredPlayer { player, .. }| {
+                    changes.current_dev(player) >= DevTrait::Star as u8
+                        && !changes.is_protected(player)
+                })
+                .sorted_by(|a, b| {
+                    let (dev_a, dev_b) =
+                        (changes.current_dev(a.player), changes.current_dev(b.player));
+                    match budget.order {
+                        BudgetOrder::LowestScore => Ordering::Equal,
+                        BudgetOrder::LowestTrait => dev_a.cmp(&dev_b),
+                        BudgetOrder::HighestTrait => dev_b.cmp(&dev_a),
+                    }
+                    .then_with(|| compare_across_groups(b, a))
                 })
-                .rev()
-                .take(xf_count - limits.xf_max)
                 .collect_vec();
-            for (player, _) in players {
-                protected_players.insert(player.fullName.clone());
-                changed_players.insert(
-                    (
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    ),
-                    (
-                        unsafe { transmute::<_, DevTrait>(player.devTrait) },
-                        unsafe { transmute::<_, DevTrait>(player.devTrait - 1) },
-                    ),
-                );
+            let mut remaining = points;
+            let mut demoted = Vec::new();
+            for ScoredPlayer { player, .. } in candidates {
+                if remaining <= budget.points {
+                    break;
+                }
+                let dev = changes.current_dev(player);
+                remaining = remaining.saturating_sub(cost(dev) - cost(dev - 1));
+                changes.force_demote(player, dev);
+                demoted.push(format!(
+                    "{} {} ({:?} -> {:?})",
+                    player.position,
+                    player.fullName,
+                    unsafe { transmute::<u8, DevTrait>(dev) },
+                    unsafe { transmute::<u8, DevTrait>(dev - 1) }
+                ));
             }
+            team_budget_breaches.push(format!(
+                "{team} spent {points} points with a budget of {}, demoted {}{}",
+                budget.points,
+                if demoted.is_empty() {
+                    "nobody".to_string()
+                } else {
+                    demoted.join(", ")
+                },
+                if remaining > budget.points {
+                    format!(" and is still at {remaining} (everyone else is protected)")
+                } else {
+                    String::new()
+                }
+            ));
         }
+    }
 
-        if debug {
+    for (pos, players) in &ranked_groups {
+        if output == Output::Debug {
             println!("{pos}:");
-            for (player, score) in &players {
+            for ScoredPlayer {
+                player,
+                season_score,
+                score,
+                z_score,
+                percentile,
+                ovr_percentile,
+                ..
+            } in players
+            {
                 println!(
-                    "{} = {score:.2}: {}",
+                    "{} ({}) = {score:.2} ({}z {z_score:+.2}, {percentile:.0}%, OVR {ovr_percentile:.0}%): {}",
                     player.fullName,
-                    if let Some(changed) = changed_players.get(&(
-                        player.fullName.clone(),
-                        player.team.clone(),
-                        player.position.clone(),
-                    )) {
-                        format!("{:?} -> {:?}", changed.0, changed.1)
-                    } else if protected_players.contains(&player.fullName) {
-                        format!("{:?} (Protected)", unsafe {
-                            transmute::<_, DevTrait>(player.devTrait)
-                        })
+                    group_reason(player),
+                    if prior_stats.is_empty() {
+                        String::new()
                     } else {
-                        format!("{:?} (Unchanged)", unsafe {
-                            transmute::<_, DevTrait>(player.devTrait)
-                        })
+                        format!("this season {season_score:.2}, ")
+                    },
+                    match changes.status(player) {
+                        status @ (Status::Changed | Status::AgeDecline) => {
+                            let (old, new) = changes.changed[&key(player)];
+                            format!(
+                                "{old:?} -> {new:?}{}",
+                                if status == Status::AgeDecline {
+                                    " (age decline)"
+                                } else {
+                                    ""
+                                }
+                            )
+                        }
+                        Status::Warned => {
+                            let (old, strike) = changes.warned[&key(player)];
+                            format!(
+                                "{old:?} (Warned, strike {strike} of {})",
+                                config.demotion_strikes
+                            )
+                        }
+                        status => format!("{:?} ({status})", unsafe {
+                            transmute::<u8, DevTrait>(player.devTrait)
+                        }),
                     }
                 );
             }
             println!();
         }
+
+        for scored in players {
+            let player = scored.player;
+            let old = unsafe { transmute::<u8, DevTrait>(player.devTrait) };
+            let new = unsafe { transmute::<u8, DevTrait>(changes.current_dev(player)) };
+            score_rows.push(ScoreRow {
+                group: pos.to_string(),
+                rosterId: player.rosterId,
+                fullName: player.fullName.clone(),
+                team: player.team.clone(),
+                position: player.position.clone(),
+                groupReason: group_reason(player),
+                seasonScore: scored.season_score,
+                score: scored.score,
+                zScore: scored.z_score,
+                percentile: scored.percentile,
+                ovrPercentile: scored.ovr_percentile,
+                rankingScore: scored.ranking_score,
+                oldDevTrait: old,
+                newDevTrait: new,
+                status: changes.status(player),
+            });
+        }
     }
 
-    if !debug {
-        for (team, group) in changed_players
-            .into_iter()
+    if output == Output::Csv {
+        let mut writer = Writer::from_writer(stdout());
+        for row in &score_rows {
+            writer.serialize(row).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    if output == Output::Report {
+        let demotion_strikes = config.demotion_strikes;
+        for (team, group) in changes
+            .changed
+            .iter()
+            .map(|(key, (old, new))| {
+                let label = if changes.is_age_declined(key) {
+                    " (age decline)"
+                } else {
+                    ""
+                };
+                (key.clone(), format!("{old:?} -> {new:?}{label}"))
+            })
+            .chain(changes.warned.iter().map(|(key, (old, strike))| {
+                (
+                    key.clone(),
+                    format!("{old:?} (warned, strike {strike} of {demotion_strikes})"),
+                )
+            }))
             .sorted_unstable_by_key(|((_, team, _), _)| team.clone())
             .group_by(|((_, team, _), _)| team.clone())
             .into_iter()
@@ -655,346 +868,401 @@ fn main() {
             } else {
                 println!("{team}:");
             }
-            for ((player, _, pos), (old, new)) in group.into_iter().sorted_unstable_by(
+            for ((player, _, pos), change) in group.into_iter().sorted_unstable_by(
                 |((name_a, _, pos_a), _), ((name_b, _, pos_b), _)| {
                     if pos_a == pos_b {
                         return name_a.cmp(name_b);
                     }
-                    get_pos_sort_order(pos_a).cmp(&get_pos_sort_order(pos_b))
+                    positions
+                        .sort_order(pos_a)
+                        .cmp(&positions.sort_order(pos_b))
                 },
             ) {
-                println!("{pos} {player}: {old:?} -> {new:?}");
+                match position_changes.values().find(|moved| {
+                    moved.fullName == player && moved.team == team && moved.position == pos
+                }) {
+                    Some(moved) => {
+                        println!("{pos} {player}: {change} (moved from {})", moved.old)
+                    }
+                    None => println!("{pos} {player}: {change}"),
+                }
+            }
+            println!();
+        }
+
+        if !position_changes.is_empty() {
+            println!(
+                "Position changes ({}):",
+                match config.position_changes {
+                    PositionChangePolicy::Season => "judged at their old position",
+                    PositionChangePolicy::New => "judged at their new position",
+                    PositionChangePolicy::Exempt => "exempt from changes",
+                }
+            );
+            for moved in position_changes
+                .values()
+                .sorted_by(|a, b| (&a.fullName, a.rosterId).cmp(&(&b.fullName, b.rosterId)))
+            {
+                println!("{}: {} -> {}", moved.fullName, moved.old, moved.new);
             }
             println!();
         }
     }
-}
 
-fn get_pos_sort_order(pos: &str) -> u8 {
-    match pos {
-        "QB" => 0,
-        "HB" => 1,
-        "FB" => 2,
-        "WR" => 3,
-        "TE" => 4,
-        "LT" => 5,
-        "LG" => 6,
-        "C" => 7,
-        "RG" => 8,
-        "RT" => 9,
-        "LE" => 10,
-        "RE" => 11,
-        "DT" => 12,
-        "LOLB" => 13,
-        "MLB" => 14,
-        "ROLB" => 15,
-        "CB" => 16,
-        "FS" => 17,
-        "SS" => 18,
-        "K" => 19,
-        "P" => 20,
-        _ => unreachable!(),
+    // Only worth showing in the report if free agents are being treated
+    // differently
+    if (output == Output::Debug
+        || (output == Output::Report && config.free_agents != FreeAgentPolicy::Include))
+        && !free_agent_summary.is_empty()
+    {
+        println!("Free agents:");
+        for line in free_agent_summary {
+            println!("{line}");
+        }
+        println!();
     }
-}
 
-fn read_csv<T: DeserializeOwned>(filename: &str) -> Vec<T> {
-    let file = BufReader::new(File::open(filename).unwrap());
-    let mut reader = Reader::from_reader(file);
-    reader.deserialize().map(|rec| rec.unwrap()).collect()
-}
+    if output != Output::Csv && !team_cap_breaches.is_empty() {
+        println!("Teams over a cap:");
+        for breach in team_cap_breaches {
+            println!("{breach}");
+        }
+        println!();
+    }
 
-fn from_str_bool<'de, D>(de: D) -> Result<bool, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(de)?;
-    if s == "True" {
-        Ok(true)
-    } else if s == "False" {
-        Ok(false)
-    } else {
-        panic!("Unexpected value for bool");
+    if output != Output::Csv && !team_budget_breaches.is_empty() {
+        println!("Teams over budget:");
+        for breach in team_budget_breaches {
+            println!("{breach}");
+        }
+        println!();
     }
-}
 
-const PASS_YARD_VALUE: f32 = 0.05;
-const PASS_TD_VALUE: f32 = 4.0;
-const INT_LOST_VALUE: f32 = -2.0;
-const RUSH_YARD_VALUE: f32 = 0.1;
-const RUSH_TD_VALUE: f32 = 4.0;
-const FUMBLE_VALUE: f32 = -2.0;
-const RECV_YARD_VALUE: f32 = 0.1;
-const RECV_CATCH_VALUE: f32 = 0.25;
-const RECV_DROP_VALUE: f32 = -0.05;
-const RECV_TD_VALUE: f32 = 4.0;
-const TACKLE_VALUE: f32 = 0.5;
-const DEFLECTION_VALUE: f32 = 1.0;
-const CATCH_ALLOWED_VALUE: f32 = -0.2;
-const SACK_VALUE: f32 = 2.0;
-const DEF_TD_VALUE: f32 = 6.0;
-const FORCED_FUMBLE_VALUE: f32 = 2.0;
-const FUMBLE_REC_VALUE: f32 = 2.0;
-const INT_FORCED_VALUE: f32 = 3.0;
-const INT_RETURN_YARD_VALUE: f32 = 0.05;
-const SAFETY_VALUE: f32 = 3.0;
-const FG_VALUE: f32 = 3.0;
-const FG_50_PLUS_VALUE: f32 = 5.0;
-const FG_MISS_VALUE: f32 = -1.0;
-// This is positive to reduce the penalty from above.
-const FG_50_PLUS_MISS_VALUE: f32 = 0.9;
-const XP_VALUE: f32 = 1.0;
-const XP_MISS_VALUE: f32 = -1.0;
-const PUNT_AVG_YDS_VALUE: f32 = 0.02;
-
-/// Applies a multiplier to a score based on a players age and possibly other
-/// stuff in the future
-fn calc_player_multiplier(player: &PlayerData) -> f32 {
-    if player.age <= 24 || player.yearsPro <= 2 {
-        return 1.2;
+    if output != Output::Csv && !skipped_promotions.is_empty() {
+        println!("Skipped for promotion:");
+        for skipped in skipped_promotions {
+            println!("{skipped}");
+        }
+        println!();
     }
-    if player.age <= 26 {
-        return 1.1;
+
+    if output != Output::Csv && !on_the_bubble.is_empty() {
+        println!("On the bubble:");
+        for line in on_the_bubble {
+            println!("{line}");
+        }
+        println!();
     }
-    if player.age <= 29 {
-        return 1.0;
+
+    if output != Output::Csv && !merit_swaps.is_empty() {
+        println!("Merit swaps:");
+        for swap in merit_swaps {
+            println!("{swap}");
+        }
+        println!();
     }
-    // A gradual slope that results in 20% loss at age 35, 40% loss at age 40, to a
-    // max of 50%.
-    (1.0 + (player.age - 30) as f32 * -0.04).max(0.5)
-}
 
-fn calc_qb_score(
-    player: &PlayerData,
-    pass_stats: Option<&PassingData>,
-    rush_stats: Option<&RushingData>,
-) -> f32 {
-    calc_player_multiplier(player)
-        * (pass_stats
-            .map(|stat| {
-                stat.passTotalYds as f32 * PASS_YARD_VALUE
-                    + stat.passTotalTDs as f32 * PASS_TD_VALUE
-                    + stat.passTotalInts as f32 * INT_LOST_VALUE
-            })
-            .unwrap_or(0.0)
-            + rush_stats
-                .map(|stat| {
-                    stat.rushTotalYds as f32 * RUSH_YARD_VALUE
-                        + stat.rushTotalTDs as f32 * RUSH_TD_VALUE
-                        + stat.rushTotalFum as f32 * FUMBLE_VALUE
-                })
-                .unwrap_or(0.0))
+    if output != Output::Csv && !cutoff_ties.is_empty() {
+        println!("Ties at a cutoff (decided by overall, then age, then roster id):");
+        for tie in cutoff_ties {
+            println!("{tie}");
+        }
+        println!();
+    }
+
+    if output != Output::Csv && bubble_watch {
+        println!("Bubble watch (distance from each cutoff, closest first):");
+        for line in bubble::watch(&score_rows) {
+            println!("{line}");
+        }
+    }
+
+    if record {
+        history::append(&SeasonRecord::new(inputs_hash, config, score_rows));
+        eprintln!("Recorded this season in {HISTORY_PATH}");
+    }
 }
 
-fn calc_receiver_score(
-    player: &PlayerData,
-    recv_stats: Option<&ReceivingData>,
-    rush_stats: Option<&RushingData>,
-) -> f32 {
-    calc_player_multiplier(player)
-        * (recv_stats
-            .map(|stat| {
-                stat.recTotalYds as f32 * RECV_YARD_VALUE
-                    + stat.recTotalTDs as f32 * RECV_TD_VALUE
-                    + stat.recTotalCatches as f32 * RECV_CATCH_VALUE
-                    + stat.recTotalDrops as f32 * RECV_DROP_VALUE
-            })
-            .unwrap_or(0.0)
-            + rush_stats
-                .map(|stat| {
-                    stat.rushTotalYds as f32 * RUSH_YARD_VALUE
-                        + stat.rushTotalTDs as f32 * RUSH_TD_VALUE
-                        + stat.rushTotalFum as f32 * FUMBLE_VALUE
-                })
-                .unwrap_or(0.0))
+/// One line for the summary at the end, or `None` if the group has no free
+/// agents.
+fn describe_free_agents(
+    pos: &str,
+    free_agents: &[ScoredPlayer],
+    policy: FreeAgentPolicy,
+    own_limits: Option<&DevLimits>,
+) -> Option<String> {
+    if free_agents.is_empty() {
+        return None;
+    }
+    let count = |dev: DevTrait| {
+        free_agents
+            .iter()
+            .filter(|ScoredPlayer { player, .. }| player.devTrait >= dev as u8)
+            .count()
+    };
+    Some(format!(
+        "{pos}: {} free agent{} ({} Star+, {} Superstar+, {} XFactor), {}",
+        free_agents.len(),
+        if free_agents.len() == 1 { "" } else { "s" },
+        count(DevTrait::Star),
+        count(DevTrait::Superstar),
+        count(DevTrait::XFactor),
+        match (policy, own_limits) {
+            (FreeAgentPolicy::Separate, Some(limits)) => format!(
+                "judged on their own quota (at most Star {}, Superstar {}, XFactor {})",
+                limits.star_max, limits.ss_max, limits.xf_max
+            ),
+            (FreeAgentPolicy::ExcludeCounts, _) => {
+                "left out of the quota and unchanged".to_string()
+            }
+            (FreeAgentPolicy::ExcludePromotions, _) => {
+                "counted toward the quota but can't be promoted".to_string()
+            }
+            _ => "counted toward the quota".to_string(),
+        }
+    ))
 }
 
-/// The game doesn't give us stats for OL so we have to do something weird and
-/// terrible. Something very madden-esque. Although we at least take into
-/// account age here, and add in a random factor so it's not just the youngest,
-/// best players getting it. But this still sucks. Blame Madden for not giving
-/// us OL stats.
-fn calc_ol_score(player: &PlayerData, rng: &mut StdRng) -> f32 {
-    calc_player_multiplier(player) * player.playerBestOvr as f32 * rng.gen_range(0.9..1.1)
+/// Demotes `count` of `candidates`, who are every holder who could be demoted,
+/// lowest first, less anyone spared by the grace band. They're listed in
+/// `on_the_bubble`, and a tie at the cutoff in `cutoff_ties`, both under
+/// `label`.
+fn demote_overflow(
+    label: &str,
+    candidates: &[&ScoredPlayer],
+    count: usize,
+    grace_band: Option<f32>,
+    changes: &mut Changes,
+    on_the_bubble: &mut Vec<String>,
+    cutoff_ties: &mut Vec<String>,
+) {
+    let mut picked = candidates.iter().copied().take(count).collect_vec();
+    if let Some(band) = grace_band {
+        for (line, spared) in apply_grace_band(candidates, &mut picked, band) {
+            changes.protect(spared);
+            on_the_bubble.push(format!("{label}: {line}"));
+        }
+    }
+    // After the grace band, since it can change who's at the cutoff
+    if let Some((last, next, score)) = cutoff_tie(candidates, &picked) {
+        cutoff_ties.push(format!(
+            "{label}: {} and {} both scored {score:.2}, {} was picked by the tie-breakers",
+            last.fullName, next.fullName, last.fullName
+        ));
+    }
+    for ScoredPlayer { player, .. } in picked {
+        changes.demote(player);
+    }
 }
 
-fn calc_defense_score(player: &PlayerData, stats: Option<&DefenseData>) -> f32 {
-    calc_player_multiplier(player)
-        * stats
-            .map(|stat| {
-                stat.defTotalTackles * TACKLE_VALUE
-                    + stat.defTotalDeflections as f32 * DEFLECTION_VALUE
-                    + stat.defTotalCatchAllowed as f32 * CATCH_ALLOWED_VALUE
-                    + stat.defTotalSacks * SACK_VALUE
-                    + stat.defTotalTDs as f32 * DEF_TD_VALUE
-                    + stat.defTotalForcedFum as f32 * FORCED_FUMBLE_VALUE
-                    + stat.defTotalFumRec as f32 * FUMBLE_REC_VALUE
-                    + stat.defTotalInts as f32 * INT_FORCED_VALUE
-                    + stat.defTotalIntReturnYds as f32 * INT_RETURN_YARD_VALUE
-                    + stat.defTotalSafeties as f32 * SAFETY_VALUE
-            })
-            .unwrap_or(0.0)
+/// `candidates` are every holder who could be demoted, lowest first, and
+/// `picked` are the ones being demoted, from the front of `candidates`. Anyone
+/// picked who ranked within `band` percent below the last player who keeps
+/// their trait is on the bubble, and is taken out of `picked`. Nobody is
+/// demoted in their place, since everyone below them already is, so the group
+/// stays over its maximum by one for each of them. Returns a line for each
+/// spared player, along with them.
+fn apply_grace_band<'a, 'p>(
+    candidates: &[&'a ScoredPlayer<'p>],
+    picked: &mut Vec<&'a ScoredPlayer<'p>>,
+    band: f32,
+) -> Vec<(String, &'p PlayerData)> {
+    let Some(last_safe) = candidates.get(picked.len()) else {
+        return Vec::new();
+    };
+    let cutoff = last_safe.ranking_score;
+    let threshold = cutoff - cutoff.abs() * band / 100.0;
+    let first_spared = picked
+        .iter()
+        .position(|candidate| candidate.ranking_score >= threshold)
+        .unwrap_or(picked.len());
+    // Highest-ranked first, since they're the closest to being safe
+    picked
+        .split_off(first_spared)
+        .into_iter()
+        .rev()
+        .map(|spared| {
+            // A band around a cutoff of 0 has no width, so anyone here is tied with it
+            let below = if cutoff == 0.0 {
+                0.0
+            } else {
+                (cutoff - spared.ranking_score) / cutoff.abs() * 100.0
+            };
+            (
+                format!(
+                    "{} ({:.2}, {below:.1}% below {}) was spared",
+                    spared.player.fullName, spared.ranking_score, last_safe.player.fullName
+                ),
+                spared.player,
+            )
+        })
+        .collect()
 }
 
-fn calc_kicker_score(player: &PlayerData, stats: Option<&KickingData>) -> f32 {
-    calc_player_multiplier(player)
-        * stats
-            .map(|stat| {
-                let fg_miss = stat.fGTotalAtt - stat.fGTotalMade;
-                let fg_50plus_miss = stat.fGTotal50PlusAtt - stat.fGTotal50PlusMade;
-                let xp_miss = stat.xPTotalAtt - stat.xPTotalMade;
-                stat.fGTotalMade as f32 * FG_VALUE
-                    + stat.fGTotal50PlusMade as f32 * FG_50_PLUS_VALUE
-                    + fg_miss as f32 * FG_MISS_VALUE
-                    + fg_50plus_miss as f32 * FG_50_PLUS_MISS_VALUE
-                    + stat.xPTotalMade as f32 * XP_VALUE
-                    + xp_miss as f32 * XP_MISS_VALUE
-            })
-            .unwrap_or(0.0)
+/// Anyone in the first `count` candidates who was ruled out by `block`, with
+/// the reason, since they'd have been promoted otherwise.
+fn skipped_candidates<'p>(
+    candidates: &[&ScoredPlayer<'p>],
+    count: usize,
+    block: impl Fn(&PlayerData) -> Option<String>,
+) -> Vec<(&'p PlayerData, String)> {
+    candidates
+        .iter()
+        .take(count)
+        .filter_map(|ScoredPlayer { player, .. }| Some((*player, block(player)?)))
+        .collect()
 }
 
-fn calc_punter_score(player: &PlayerData, stats: Option<&PuntingData>) -> f32 {
-    calc_player_multiplier(player)
-        * stats
-            .map(|stat| {
-                if stat.gamesPlayed < 6 {
-                    // Because this is based on averages, given that good teams punt less,
-                    // we need to have a minimum number of games played to consider a punter for
-                    // promotion. On all the other positions, the stats have a
-                    // direct correlation with games played.
-                    //
-                    // Except OL. Because we have no stats. Not even number of games played.
-                    return stat.gamesPlayed as f32 / 100.;
-                }
-                stat.puntAvgYdsPerAtt * PUNT_AVG_YDS_VALUE
-                    + stat.puntsTotalIn20 as f32 / stat.puntTotalAtt as f32
-            })
-            .unwrap_or(0.0)
+/// Takes the first `count` candidates, along with the tie at the cutoff if
+/// there is one.
+#[allow(clippy::type_complexity)]
+fn take_with_cutoff<'a, 'p>(
+    candidates: impl Iterator<Item = &'a ScoredPlayer<'p>>,
+    count: usize,
+) -> (
+    Vec<&'a ScoredPlayer<'p>>,
+    Option<(&'p PlayerData, &'p PlayerData, f32)>,
+) {
+    let candidates = candidates.collect_vec();
+    let picked = candidates.iter().copied().take(count).collect_vec();
+    let tie = cutoff_tie(&candidates, &picked);
+    (picked, tie)
 }
 
-#[derive(Debug, Clone, Copy)]
-struct DevLimits {
-    pub xf_min: usize,
-    pub xf_max: usize,
-    pub ss_min: usize,
-    pub ss_max: usize,
-    pub star_min: usize,
-    pub star_max: usize,
+/// If the last of `picked` in line has the same score as the next candidate
+/// who wasn't picked, the cutoff was decided by the tie-breakers, so both of
+/// them are returned to be flagged.
+fn cutoff_tie<'p>(
+    candidates: &[&ScoredPlayer<'p>],
+    picked: &[&ScoredPlayer<'p>],
+) -> Option<(&'p PlayerData, &'p PlayerData, f32)> {
+    let is_picked = |candidate: &&&ScoredPlayer| picked.iter().any(|p| ptr::eq(*p, **candidate));
+    let last = candidates.iter().rev().find(is_picked)?;
+    let next = candidates.iter().find(|candidate| !is_picked(candidate))?;
+    (last.ranking_score == next.ranking_score).then_some((
+        last.player,
+        next.player,
+        last.ranking_score,
+    ))
 }
 
-#[derive(Deserialize)]
-struct PlayerData {
+#[derive(Serialize, Deserialize)]
+struct ScoreRow {
+    pub group: String,
     pub rosterId: u32,
-    pub team: String,
-    #[serde(deserialize_with = "from_str_bool")]
-    pub isRetired: bool,
-    pub age: u8,
     pub fullName: String,
+    pub team: String,
     pub position: String,
-    pub playerBestOvr: u8,
-    pub yearsPro: u8,
-    pub devTrait: u8,
+    pub groupReason: String,
+    /// Only differs from `score` when prior seasons are blended in.
+    #[serde(default)]
+    pub seasonScore: f32,
+    pub score: f32,
+    pub zScore: f32,
+    pub percentile: f32,
+    pub ovrPercentile: f32,
+    pub rankingScore: f32,
+    pub oldDevTrait: DevTrait,
+    pub newDevTrait: DevTrait,
+    pub status: Status,
 }
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct PassingData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub passTotalAtt: u32,
-    pub passTotalComp: u32,
-    pub passAvgCompPct: f32,
-    pub passTotalInts: u32,
-    pub passTotalLongest: i32,
-    pub passerAvgRating: f32,
-    pub passTotalSacks: u32,
-    pub passTotalTDs: u32,
-    pub passTotalYds: i32,
-    pub passAvgYdsPerAtt: f32,
-    pub passAvgYdsPerGame: f32,
-}
+#[cfg(test)]
+mod tests {
+    use csv::Reader;
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct ReceivingData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub recTotalCatches: u32,
-    pub recAvgCatchPct: f32,
-    pub recTotalDrops: u32,
-    pub recTotalLongest: u32,
-    pub recTotalTDs: u32,
-    pub recTotalYdsAfterCatch: i32,
-    pub recTotalYds: i32,
-    pub recAvgYacPerCatch: f32,
-    pub recAvgYdsPerCatch: f32,
-    pub recAvgYdsPerGame: f32,
-}
+    use super::*;
+    use crate::config::RankBy;
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct RushingData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub rushTotalAtt: u32,
-    pub rushTotalBrokenTackles: u32,
-    pub rushTotalFum: u32,
-    pub rushTotalLongest: i32,
-    pub rushTotalTDs: u32,
-    pub rushTotal20PlusYds: u32,
-    pub rushTotalYdsAfterContact: i32,
-    pub rushTotalYds: i32,
-    pub rushAvgYdsAfterContact: f32,
-    pub rushAvgYdsPerAtt: f32,
-    pub rushAvgYdsPerGame: f32,
-}
+    #[test]
+    fn ties_are_broken_by_ovr_then_age_then_roster_id() {
+        let players: Vec<PlayerData> = Reader::from_reader(
+            "rosterId,team,isRetired,age,fullName,position,playerBestOvr,yearsPro,devTrait\n\
+             5,Bears,False,28,Higher Id,WR,70,3,1\n\
+             1,Bears,False,28,Lower Id,WR,70,3,1\n\
+             2,Bears,False,24,Younger,WR,70,3,1\n\
+             3,Bears,False,30,Higher Ovr,WR,80,3,1\n\
+             4,Bears,False,30,Best Score,WR,60,3,1\n"
+                .as_bytes(),
+        )
+        .deserialize()
+        .map(|row| row.unwrap())
+        .collect();
+        let scores = players
+            .iter()
+            .map(|player| {
+                let score = if player.fullName == "Best Score" {
+                    10.0
+                } else {
+                    5.0
+                };
+                (player, score, score)
+            })
+            .collect_vec();
+        let ranked = rank_group(scores, RankBy::Score, 0.0);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|ScoredPlayer { player, .. }| player.fullName.as_str())
+                .collect_vec(),
+            [
+                "Best Score",
+                "Higher Ovr",
+                "Younger",
+                "Lower Id",
+                "Higher Id"
+            ]
+        );
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct DefenseData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub defTotalCatchAllowed: u32,
-    pub defTotalDeflections: u32,
-    pub defTotalForcedFum: u32,
-    pub defTotalFumRec: u32,
-    pub defTotalInts: u32,
-    pub defTotalIntReturnYds: u32,
-    pub defTotalSacks: f32,
-    pub defTotalSafeties: u32,
-    pub defTotalTDs: u32,
-    pub defTotalTackles: f32,
-}
+        // Higher Ovr and Younger both scored 5, so the cutoff after two is a tie
+        let (picked, tie) = take_with_cutoff(ranked.iter(), 2);
+        assert_eq!(picked.len(), 2);
+        let (last, next, score) = tie.unwrap();
+        assert_eq!(
+            (last.fullName.as_str(), next.fullName.as_str(), score),
+            ("Higher Ovr", "Younger", 5.0)
+        );
+        assert!(take_with_cutoff(ranked.iter(), 1).1.is_none());
+    }
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct KickingData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub fGTotalAtt: u32,
-    pub fGTotal50PlusAtt: u32,
-    pub fGTotal50PlusMade: u32,
-    pub fGTotalLongest: u32,
-    pub fGTotalMade: u32,
-    pub fGAvgCompPct: f32,
-    pub kickoffTotalAtt: u32,
-    pub kickoffTotalTBs: u32,
-    pub xPTotalAtt: u32,
-    pub xPTotalMade: u32,
-    pub xPAvgCompPct: f32,
-}
+    #[test]
+    fn grace_band_spares_players_just_below_the_cutoff() {
+        let players: Vec<PlayerData> = Reader::from_reader(
+            "rosterId,team,isRetired,age,fullName,position,playerBestOvr,yearsPro,devTrait\n\
+             1,Bears,False,25,Far Below,WR,70,3,1\n\
+             2,Bears,False,25,Below,WR,70,3,1\n\
+             3,Bears,False,25,Close,WR,70,3,1\n\
+             4,Bears,False,25,Cutoff,WR,70,3,1\n\
+             5,Bears,False,25,Safe,WR,70,3,1\n"
+                .as_bytes(),
+        )
+        .deserialize()
+        .map(|row| row.unwrap())
+        .collect();
+        let scores = players
+            .iter()
+            .zip([1.0, 5.0, 9.8, 10.0, 12.0])
+            .map(|(player, score)| (player, score, score))
+            .collect_vec();
+        let ranked = rank_group(scores, RankBy::Score, 0.0);
+        let candidates = ranked.iter().rev().collect_vec();
+        let mut picked = candidates.iter().copied().take(3).collect_vec();
+
+        let spared = apply_grace_band(&candidates, &mut picked, 3.0);
+        assert_eq!(spared.len(), 1);
+        assert_eq!(spared[0].1.fullName, "Close");
+        assert_eq!(spared[0].0, "Close (9.80, 2.0% below Cutoff) was spared");
+        assert_eq!(
+            picked
+                .iter()
+                .map(|ScoredPlayer { player, .. }| player.fullName.as_str())
+                .collect_vec(),
+            ["Far Below", "Below"]
+        );
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct PuntingData {
-    pub player__rosterId: u32,
-    pub gamesPlayed: u8,
-    pub puntsTotalBlocked: u32,
-    pub puntsTotalIn20: u32,
-    pub puntTotalLongest: i32,
-    pub puntTotalTBs: u32,
-    pub puntAvgNetYdsPerAtt: f32,
-    pub puntTotalNetYds: i32,
-    pub puntTotalAtt: u32,
-    pub puntAvgYdsPerAtt: f32,
-    pub puntTotalYds: i32,
+        // Nobody within 1% of the cutoff
+        let mut picked = candidates.iter().copied().take(3).collect_vec();
+        assert!(apply_grace_band(&candidates, &mut picked, 1.0).is_empty());
+        assert_eq!(picked.len(), 3);
+    }
 }
@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use crate::{config::Config, data::PlayerData};
+
+/// Madden renamed the front seven in 25, so which names we expect depends on
+/// the game the league is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaddenVersion {
+    /// LE/RE/DT and LOLB/MLB/ROLB
+    Madden24,
+    /// LEDGE/REDGE/DT and SAM/MIKE/WILL
+    Madden25,
+}
+
+impl MaddenVersion {
+    /// Neon's export headers are the same between versions, so the only way to
+    /// tell is by the positions themselves.
+    pub fn detect(players: &[PlayerData]) -> Self {
+        if players.iter().any(|player| {
+            ["LEDGE", "REDGE", "SAM", "MIKE", "WILL"].contains(&player.position.as_str())
+        }) {
+            MaddenVersion::Madden25
+        } else {
+            MaddenVersion::Madden24
+        }
+    }
+}
+
+impl Display for MaddenVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaddenVersion::Madden24 => write!(f, "Madden 24 or earlier"),
+            MaddenVersion::Madden25 => write!(f, "Madden 25+"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionInfo {
+    /// The position group this position is judged in, e.g. `EDGE`.
+    pub group: String,
+    /// The group to use instead for teams running a 3-4, if it's different.
+    pub three_four_group: Option<String>,
+    /// Where the position goes when listing players, lowest first.
+    pub sort_order: u8,
+}
+
+/// Maps raw Madden positions to the groups they're judged in. The built-in
+/// mapping can be changed or added to with `[positions.X]` in the config.
+pub struct PositionCatalog {
+    pub version: MaddenVersion,
+    positions: HashMap<String, PositionInfo>,
+    three_four_teams: Vec<String>,
+    /// Raw position -> archetype -> group, empty if the classifier is off.
+    archetypes: HashMap<String, HashMap<String, String>>,
+    /// rosterId -> group
+    overrides: HashMap<u32, String>,
+}
+
+/// Which group a player ended up in, and why.
+pub struct Classification<'a> {
+    pub group: &'a str,
+    pub reason: String,
+}
+
+impl PositionCatalog {
+    pub fn new(version: MaddenVersion, config: &Config) -> Self {
+        let front_seven: &[(&str, &str, Option<&str>)] = match version {
+            MaddenVersion::Madden24 => &[
+                ("LE", "EDGE", Some("IDL")),
+                ("RE", "EDGE", Some("IDL")),
+                ("DT", "IDL", None),
+                ("LOLB", "LB", Some("EDGE")),
+                ("MLB", "LB", None),
+                ("ROLB", "LB", Some("EDGE")),
+            ],
+            // Edge rushers are listed as such no matter the scheme
+            MaddenVersion::Madden25 => &[
+                ("LEDGE", "EDGE", None),
+                ("REDGE", "EDGE", None),
+                ("DT", "IDL", None),
+                ("SAM", "LB", None),
+                ("MIKE", "LB", None),
+                ("WILL", "LB", None),
+            ],
+        };
+        let offense: &[(&str, &str, Option<&str>)] = &[
+            ("QB", "QB", None),
+            ("HB", "HB", None),
+            ("FB", "FB", None),
+            ("WR", "WR", None),
+            ("TE", "TE", None),
+            ("LT", "OL", None),
+            ("LG", "OL", None),
+            ("C", "OL", None),
+            ("RG", "OL", None),
+            ("RT", "OL", None),
+        ];
+        let back_end: &[(&str, &str, Option<&str>)] = &[
+            ("CB", "CB", None),
+            ("FS", "S", None),
+            ("SS", "S", None),
+            ("K", "K", None),
+            ("P", "P", None),
+        ];
+
+        let mut positions: HashMap<String, PositionInfo> = offense
+            .iter()
+            .chain(front_seven)
+            .chain(back_end)
+            .enumerate()
+            .map(|(i, (position, group, three_four_group))| {
+                (
+                    position.to_string(),
+                    PositionInfo {
+                        group: group.to_string(),
+                        three_four_group: three_four_group.map(str::to_string),
+                        sort_order: i as u8,
+                    },
+                )
+            })
+            .collect();
+        for (position, position_config) in &config.positions {
+            let sort_order = position_config.sort_order.unwrap_or_else(|| {
+                positions
+                    .get(position)
+                    .map(|info| info.sort_order)
+                    .unwrap_or(u8::MAX)
+            });
+            positions.insert(
+                position.clone(),
+                PositionInfo {
+                    group: position_config.group.clone(),
+                    three_four_group: position_config.three_four_group.clone(),
+                    sort_order,
+                },
+            );
+        }
+
+        Self {
+            version,
+            positions,
+            three_four_teams: config.three_four_teams.clone(),
+            archetypes: if config.classifier.enabled {
+                config.classifier.archetypes.clone()
+            } else {
+                HashMap::new()
+            },
+            overrides: config
+                .classifier
+                .overrides
+                .iter()
+                .map(|(roster_id, group)| {
+                    let roster_id = roster_id.parse().unwrap_or_else(|_| {
+                        panic!("Classifier override {roster_id} is not a rosterId")
+                    });
+                    (roster_id, group.clone())
+                })
+                .collect(),
+        }
+    }
+
+    /// Every group a position can be judged in, in the order of their first
+    /// position.
+    pub fn groups<'a>(&'a self) -> Vec<&'a str> {
+        let mut groups: Vec<(&'a str, u8)> = Vec::new();
+        let mut add =
+            |group: &'a str, sort_order: u8| match groups.iter_mut().find(|(g, _)| *g == group) {
+                Some((_, order)) => *order = (*order).min(sort_order),
+                None => groups.push((group, sort_order)),
+            };
+        for (position, info) in &self.positions {
+            add(&info.group, info.sort_order);
+            if let Some(group) = &info.three_four_group {
+                add(group, info.sort_order);
+            }
+            for group in self
+                .archetypes
+                .get(position)
+                .into_iter()
+                .flat_map(|a| a.values())
+            {
+                add(group, info.sort_order);
+            }
+        }
+        for group in self.overrides.values() {
+            add(group, u8::MAX);
+        }
+        groups.sort_by_key(|&(group, order)| (order, group));
+        groups.into_iter().map(|(group, _)| group).collect()
+    }
+
+    /// Returns `None` for positions this version of Madden doesn't have, unless
+    /// the player has an override.
+    pub fn classify(&self, player: &PlayerData) -> Option<Classification<'_>> {
+        self.classify_as(player, &player.position)
+    }
+
+    /// Same as `classify`, but as if the player were listed at `position`.
+    pub fn classify_as(&self, player: &PlayerData, position: &str) -> Option<Classification<'_>> {
+        if let Some(group) = self.overrides.get(&player.rosterId) {
+            return Some(Classification {
+                group,
+                reason: format!("{position}, override"),
+            });
+        }
+        if let Some(archetype) = player.archetype.as_deref() {
+            if let Some(group) = self
+                .archetypes
+                .get(position)
+                .and_then(|archetypes| archetypes.get(archetype))
+            {
+                return Some(Classification {
+                    group,
+                    reason: format!("{position}, {archetype}"),
+                });
+            }
+        }
+        let info = self.positions.get(position)?;
+        Some(match &info.three_four_group {
+            Some(group) if self.three_four_teams.contains(&player.team) => Classification {
+                group,
+                reason: format!("{position}, 3-4"),
+            },
+            _ => Classification {
+                group: &info.group,
+                reason: position.to_string(),
+            },
+        })
+    }
+
+    /// Unknown positions go last.
+    pub fn sort_order(&self, position: &str) -> u8 {
+        self.positions
+            .get(position)
+            .map(|info| info.sort_order)
+            .unwrap_or(u8::MAX)
+    }
+}
@@ -0,0 +1,453 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    config::{Config, DefenseWeights, RankBy, ScorerConfig, ScorerKind, ScoringMode},
+    data::{
+        DefenseData, KickingData, PassingData, PlayerData, PuntingData, ReceivingData, RushingData,
+        SeasonStats,
+    },
+    expr::Formula,
+};
+
+/// Scores a player's season. Higher is better, and scores are only ever compared
+/// against other players in the same position group.
+pub trait PositionScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, rng: &mut StdRng) -> f32;
+}
+
+pub struct QbScorer;
+
+impl PositionScorer for QbScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_qb_score(
+            player,
+            stats.passing(player.rosterId),
+            stats.rushing(player.rosterId),
+        )
+    }
+}
+
+pub struct ReceiverScorer;
+
+impl PositionScorer for ReceiverScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_receiver_score(
+            player,
+            stats.receiving(player.rosterId),
+            stats.rushing(player.rosterId),
+        )
+    }
+}
+
+pub struct OlScorer;
+
+impl PositionScorer for OlScorer {
+    fn score(&self, player: &PlayerData, _stats: &SeasonStats, rng: &mut StdRng) -> f32 {
+        calc_ol_score(player, rng)
+    }
+}
+
+pub struct DefenseScorer {
+    pub weights: DefenseWeights,
+}
+
+impl PositionScorer for DefenseScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_defense_score(player, stats.defense(player.rosterId), &self.weights)
+    }
+}
+
+pub struct KickerScorer;
+
+impl PositionScorer for KickerScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_kicker_score(player, stats.kicking(player.rosterId))
+    }
+}
+
+pub struct PunterScorer;
+
+impl PositionScorer for PunterScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_punter_score(player, stats.punting(player.rosterId))
+    }
+}
+
+/// Scores a player using a formula from the config. The usual age multiplier
+/// is applied on top, same as the built-in scorers.
+pub struct FormulaScorer {
+    pub formula: Formula,
+}
+
+impl PositionScorer for FormulaScorer {
+    fn score(&self, player: &PlayerData, stats: &SeasonStats, _rng: &mut StdRng) -> f32 {
+        calc_player_multiplier(player) * self.formula.eval(player, stats)
+    }
+}
+
+/// Which scorer each position group uses, as picked in the config.
+pub struct ScorerRegistry {
+    scorers: HashMap<String, Box<dyn PositionScorer>>,
+}
+
+impl ScorerRegistry {
+    /// `player_columns` are the extra columns in the players export, which
+    /// formulas are allowed to use.
+    pub fn from_config(config: &Config, player_columns: &BTreeMap<String, bool>) -> Self {
+        Self {
+            scorers: config
+                .scorers
+                .iter()
+                .map(|(group, scorer)| {
+                    let scorer: Box<dyn PositionScorer> = match scorer {
+                        ScorerConfig::Builtin(ScorerKind::Qb) => Box::new(QbScorer),
+                        ScorerConfig::Builtin(ScorerKind::Receiver) => Box::new(ReceiverScorer),
+                        ScorerConfig::Builtin(ScorerKind::Ol) => Box::new(OlScorer),
+                        ScorerConfig::Builtin(ScorerKind::Defense) => Box::new(DefenseScorer {
+                            weights: config.defense_weights(group),
+                        }),
+                        ScorerConfig::Builtin(ScorerKind::Kicker) => Box::new(KickerScorer),
+                        ScorerConfig::Builtin(ScorerKind::Punter) => Box::new(PunterScorer),
+                        ScorerConfig::Formula { formula } => Box::new(FormulaScorer {
+                            formula: Formula::parse(formula, player_columns).unwrap_or_else(|e| {
+                                panic!("Invalid formula for {group}: {e}\n    {formula}")
+                            }),
+                        }),
+                    };
+                    (group.clone(), scorer)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, group: &str) -> &dyn PositionScorer {
+        self.scorers
+            .get(group)
+            .unwrap_or_else(|| panic!("No scorer configured for position group {group}"))
+            .as_ref()
+    }
+}
+
+const PASS_YARD_VALUE: f32 = 0.05;
+const PASS_TD_VALUE: f32 = 4.0;
+const INT_LOST_VALUE: f32 = -2.0;
+const RUSH_YARD_VALUE: f32 = 0.1;
+const RUSH_TD_VALUE: f32 = 4.0;
+const FUMBLE_VALUE: f32 = -2.0;
+const RECV_YARD_VALUE: f32 = 0.1;
+const RECV_CATCH_VALUE: f32 = 0.25;
+const RECV_DROP_VALUE: f32 = -0.05;
+const RECV_TD_VALUE: f32 = 4.0;
+const FG_VALUE: f32 = 3.0;
+const FG_50_PLUS_VALUE: f32 = 5.0;
+const FG_MISS_VALUE: f32 = -1.0;
+// This is positive to reduce the penalty from above.
+const FG_50_PLUS_MISS_VALUE: f32 = 0.9;
+const XP_VALUE: f32 = 1.0;
+const XP_MISS_VALUE: f32 = -1.0;
+const PUNT_AVG_YDS_VALUE: f32 = 0.02;
+
+/// A player's score for the season, along with where that puts them within their
+/// position group. Raw scores are in whatever units the group's scorer uses (QBs
+/// score in the hundreds, punters below 2), so the z-score and percentile are
+/// what to use when comparing across groups.
+#[derive(Clone, Copy)]
+pub struct ScoredPlayer<'a> {
+    pub player: &'a PlayerData,
+    /// The score from this season's stats alone.
+    pub season_score: f32,
+    /// The score the group is normalized on. This is the same as
+    /// `season_score` unless prior seasons are blended in.
+    pub score: f32,
+    pub z_score: f32,
+    /// 0-100. Players tied on score share a percentile.
+    pub percentile: f32,
+    /// Where the player's `playerBestOvr` falls within the group, 0-100.
+    pub ovr_percentile: f32,
+    /// What the group is actually ranked by. This is whichever of the above
+    /// `rank_by` picks, unless the group blends in ratings with `ovr_blend`.
+    pub ranking_score: f32,
+}
+
+/// Blends a player's score from earlier seasons into this season's, as
+/// `(score, weight)` pairs. This season always has a weight of 1, and seasons
+/// the player didn't play in are left out rather than counting as 0.
+pub fn rolling_score(season_score: f32, prior: &[(f32, f32)]) -> f32 {
+    let total_weight = 1.0 + prior.iter().map(|(_, weight)| weight).sum::<f32>();
+    (season_score
+        + prior
+            .iter()
+            .map(|(score, weight)| score * weight)
+            .sum::<f32>())
+        / total_weight
+}
+
+/// Normalizes a group's scores and sorts it best first. If `ovr_weight` is
+/// above 0, players are ranked on a blend of their score percentile and their
+/// overall percentile, so one noisy season doesn't count for everything.
+pub fn rank_group<'a>(
+    scores: Vec<(&'a PlayerData, f32, f32)>,
+    rank_by: RankBy,
+    ovr_weight: f32,
+) -> Vec<ScoredPlayer<'a>> {
+    let n = scores.len() as f32;
+    let mean = scores.iter().map(|(_, _, score)| score).sum::<f32>() / n;
+    let std_dev = (scores
+        .iter()
+        .map(|(_, _, score)| (score - mean).powi(2))
+        .sum::<f32>()
+        / n)
+        .sqrt();
+    let ovrs = scores
+        .iter()
+        .map(|(player, ..)| player.playerBestOvr as f32)
+        .collect_vec();
+    let scores_only = scores.iter().map(|(_, _, score)| *score).collect_vec();
+
+    scores
+        .iter()
+        .map(|&(player, season_score, score)| {
+            let z_score = if std_dev > 0.0 {
+                (score - mean) / std_dev
+            } else {
+                0.0
+            };
+            let percentile = percentile_of(&scores_only, score);
+            let ovr_percentile = percentile_of(&ovrs, player.playerBestOvr as f32);
+            ScoredPlayer {
+                player,
+                season_score,
+                score,
+                z_score,
+                percentile,
+                ovr_percentile,
+                ranking_score: if ovr_weight > 0.0 {
+                    (1.0 - ovr_weight) * percentile + ovr_weight * ovr_percentile
+                } else {
+                    match rank_by {
+                        RankBy::Score => score,
+                        RankBy::ZScore => z_score,
+                        RankBy::Percentile => percentile,
+                    }
+                },
+            }
+        })
+        .sorted_by(compare_ranking)
+        .collect_vec()
+}
+
+/// Ties share the midpoint, so a group where everyone scored 0 is all at 50.
+fn percentile_of(values: &[f32], value: f32) -> f32 {
+    let below = values.iter().filter(|other| **other < value).count() as f32;
+    let tied = values.iter().filter(|other| **other == value).count() as f32;
+    (below + tied / 2.0) / values.len() as f32 * 100.0
+}
+
+/// Best players first. Ties are broken by overall, then age (younger first),
+/// then roster id, so the order never depends on the order of the CSV.
+fn compare_ranking(a: &ScoredPlayer, b: &ScoredPlayer) -> Ordering {
+    // NaN scores are rejected before ranking, so this can't fail
+    b.ranking_score
+        .partial_cmp(&a.ranking_score)
+        .unwrap()
+        .then_with(|| b.player.playerBestOvr.cmp(&a.player.playerBestOvr))
+        .then_with(|| a.player.age.cmp(&b.player.age))
+        .then_with(|| a.player.rosterId.cmp(&b.player.rosterId))
+}
+
+/// Best players first by percentile, which unlike raw scores means the same
+/// thing in every group. Ties are broken the same way as within a group.
+pub fn compare_across_groups(a: &ScoredPlayer, b: &ScoredPlayer) -> Ordering {
+    b.percentile
+        .partial_cmp(&a.percentile)
+        .unwrap()
+        .then_with(|| b.player.playerBestOvr.cmp(&a.player.playerBestOvr))
+        .then_with(|| a.player.age.cmp(&b.player.age))
+        .then_with(|| a.player.rosterId.cmp(&b.player.rosterId))
+}
+
+/// Blends a season-total score with the same score as a per-game rate. The rate
+/// is scaled up to a full season so that both halves are in the same units.
+pub fn apply_scoring_mode(
+    score: f32,
+    games_played: u8,
+    mode: ScoringMode,
+    season_games: u8,
+) -> f32 {
+    if games_played == 0 || games_played < mode.min_games {
+        return score;
+    }
+    let per_game = score / games_played as f32 * season_games as f32;
+    mode.total * score + mode.per_game * per_game
+}
+
+/// Applies a multiplier to a score based on a players age and possibly other
+/// stuff in the future
+pub fn calc_player_multiplier(player: &PlayerData) -> f32 {
+    if player.age <= 24 || player.yearsPro <= 2 {
+        return 1.2;
+    }
+    if player.age <= 26 {
+        return 1.1;
+    }
+    if player.age <= 29 {
+        return 1.0;
+    }
+    // A gradual slope that results in 20% loss at age 35, 40% loss at age 40, to a
+    // max of 50%.
+    (1.0 + (player.age - 30) as f32 * -0.04).max(0.5)
+}
+
+fn calc_qb_score(
+    player: &PlayerData,
+    pass_stats: Option<&PassingData>,
+    rush_stats: Option<&RushingData>,
+) -> f32 {
+    calc_player_multiplier(player)
+        * (pass_stats
+            .map(|stat| {
+                stat.passTotalYds as f32 * PASS_YARD_VALUE
+                    + stat.passTotalTDs as f32 * PASS_TD_VALUE
+                    + stat.passTotalInts as f32 * INT_LOST_VALUE
+            })
+            .unwrap_or(0.0)
+            + rush_stats
+                .map(|stat| {
+                    stat.rushTotalYds as f32 * RUSH_YARD_VALUE
+                        + stat.rushTotalTDs as f32 * RUSH_TD_VALUE
+                        + stat.rushTotalFum as f32 * FUMBLE_VALUE
+                })
+                .unwrap_or(0.0))
+}
+
+fn calc_receiver_score(
+    player: &PlayerData,
+    recv_stats: Option<&ReceivingData>,
+    rush_stats: Option<&RushingData>,
+) -> f32 {
+    calc_player_multiplier(player)
+        * (recv_stats
+            .map(|stat| {
+                stat.recTotalYds as f32 * RECV_YARD_VALUE
+                    + stat.recTotalTDs as f32 * RECV_TD_VALUE
+                    + stat.recTotalCatches as f32 * RECV_CATCH_VALUE
+                    + stat.recTotalDrops as f32 * RECV_DROP_VALUE
+            })
+            .unwrap_or(0.0)
+            + rush_stats
+                .map(|stat| {
+                    stat.rushTotalYds as f32 * RUSH_YARD_VALUE
+                        + stat.rushTotalTDs as f32 * RUSH_TD_VALUE
+                        + stat.rushTotalFum as f32 * FUMBLE_VALUE
+                })
+                .unwrap_or(0.0))
+}
+
+/// The game doesn't give us stats for OL so we have to do something weird and
+/// terrible. Something very madden-esque. Although we at least take into
+/// account age here, and add in a random factor so it's not just the youngest,
+/// best players getting it. But this still sucks. Blame Madden for not giving
+/// us OL stats.
+fn calc_ol_score(player: &PlayerData, rng: &mut StdRng) -> f32 {
+    calc_player_multiplier(player) * player.playerBestOvr as f32 * rng.gen_range(0.9..1.1)
+}
+
+/// Each defensive group is scored with its own weights, so e.g. CBs aren't
+/// competing with each other on tackles.
+fn calc_defense_score(
+    player: &PlayerData,
+    stats: Option<&DefenseData>,
+    weights: &DefenseWeights,
+) -> f32 {
+    calc_player_multiplier(player)
+        * stats
+            .map(|stat| {
+                stat.defTotalTackles * weights.tackle
+                    + stat.defTotalDeflections as f32 * weights.deflection
+                    + stat.defTotalCatchAllowed as f32 * weights.catch_allowed
+                    + stat.defTotalSacks * weights.sack
+                    + stat.defTotalTDs as f32 * weights.td
+                    + stat.defTotalForcedFum as f32 * weights.forced_fumble
+                    + stat.defTotalFumRec as f32 * weights.fumble_rec
+                    + stat.defTotalInts as f32 * weights.int
+                    + stat.defTotalIntReturnYds as f32 * weights.int_return_yard
+                    + stat.defTotalSafeties as f32 * weights.safety
+            })
+            .unwrap_or(0.0)
+}
+
+fn calc_kicker_score(player: &PlayerData, stats: Option<&KickingData>) -> f32 {
+    calc_player_multiplier(player)
+        * stats
+            .map(|stat| {
+                let fg_miss = stat.fGTotalAtt - stat.fGTotalMade;
+                let fg_50plus_miss = stat.fGTotal50PlusAtt - stat.fGTotal50PlusMade;
+                let xp_miss = stat.xPTotalAtt - stat.xPTotalMade;
+                stat.fGTotalMade as f32 * FG_VALUE
+                    + stat.fGTotal50PlusMade as f32 * FG_50_PLUS_VALUE
+                    + fg_miss as f32 * FG_MISS_VALUE
+                    + fg_50plus_miss as f32 * FG_50_PLUS_MISS_VALUE
+                    + stat.xPTotalMade as f32 * XP_VALUE
+                    + xp_miss as f32 * XP_MISS_VALUE
+            })
+            .unwrap_or(0.0)
+}
+
+fn calc_punter_score(player: &PlayerData, stats: Option<&PuntingData>) -> f32 {
+    calc_player_multiplier(player)
+        * stats
+            .map(|stat| {
+                // A punter who never punted has no averages at all, and dividing by
+                // their attempts would give NaN
+                if stat.gamesPlayed < 6 || stat.puntTotalAtt == 0 {
+                    // Because this is based on averages, given that good teams punt less,
+                    // we need to have a minimum number of games played to consider a punter for
+                    // promotion. On all the other positions, the stats have a
+                    // direct correlation with games played.
+                    //
+                    // Except OL. Because we have no stats. Not even number of games played.
+                    return stat.gamesPlayed as f32 / 100.;
+                }
+                stat.puntAvgYdsPerAtt * PUNT_AVG_YDS_VALUE
+                    + stat.puntsTotalIn20 as f32 / stat.puntTotalAtt as f32
+            })
+            .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::Reader;
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    fn row<T: DeserializeOwned>(csv: &str) -> T {
+        Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn punter_without_punts_is_not_nan() {
+        let player: PlayerData = row(
+            "rosterId,team,isRetired,age,fullName,position,playerBestOvr,yearsPro,devTrait\n\
+             1,Bears,False,25,Test Punter,P,70,3,0\n",
+        );
+        let stats: PuntingData = row(
+            "player__rosterId,gamesPlayed,puntsTotalBlocked,puntsTotalIn20,puntTotalLongest,\
+             puntTotalTBs,puntAvgNetYdsPerAtt,puntTotalNetYds,puntTotalAtt,puntAvgYdsPerAtt,\
+             puntTotalYds\n\
+             1,17,0,0,0,0,0,0,0,0,0\n",
+        );
+        let score = calc_punter_score(&player, Some(&stats));
+        assert!(!score.is_nan());
+    }
+}